@@ -0,0 +1,30 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use presquile::{apply, Mode};
+
+macro_rules! test_file {
+    ($file_name:expr) => {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/resources/test/", $file_name)
+    };
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("valid chaps");
+    for (name, mode) in [
+        ("sequential", Mode::Sequential),
+        ("parallel", Mode::Parallel),
+    ] {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &mode, |b, mode| {
+            b.iter(|| {
+                apply(
+                    black_box(test_file!("valid_chaps.cvs").into()),
+                    black_box(test_file!("audio.mp3").into()),
+                    *mode,
+                )
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,43 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use presquile::{batch, batch_parallel, Mode};
+use std::{
+    env::temp_dir,
+    fs::{copy, create_dir_all, remove_dir_all},
+    path::PathBuf,
+};
+
+macro_rules! test_file {
+    ($file_name:expr) => {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/resources/test/", $file_name)
+    };
+}
+
+const FILES: usize = 16;
+
+fn batch_dir() -> PathBuf {
+    let dir = temp_dir().join("presquile_batch_bench");
+    let _ = remove_dir_all(&dir);
+    create_dir_all(&dir).unwrap();
+    for idx in 0..FILES {
+        copy(test_file!("audio.mp3"), dir.join(format!("{idx}.mp3"))).unwrap();
+        copy(
+            test_file!("valid_chaps.cvs"),
+            dir.join(format!("{idx}.cvs")),
+        )
+        .unwrap();
+    }
+    dir
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let dir = batch_dir();
+    c.bench_function("batch serial", |b| {
+        b.iter(|| batch(black_box(dir.clone()), Mode::Sequential))
+    });
+    c.bench_function("batch pooled", |b| {
+        b.iter(|| batch_parallel(black_box(dir.clone()), 0))
+    });
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
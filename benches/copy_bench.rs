@@ -0,0 +1,48 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use presquile::{apply_with_options, ApplyOptions, Mode};
+use std::{
+    env::temp_dir,
+    fs::{copy, create_dir_all, remove_dir_all},
+    path::PathBuf,
+};
+
+macro_rules! test_file {
+    ($file_name:expr) => {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/resources/test/", $file_name)
+    };
+}
+
+//Every iteration gets an untouched mp3, so tags written by the previous one don't pile up
+fn fresh_copy(dir: &PathBuf) -> PathBuf {
+    let _ = remove_dir_all(dir);
+    create_dir_all(dir).unwrap();
+    let mp3_file = dir.join("audio.mp3");
+    copy(test_file!("audio.mp3"), &mp3_file).unwrap();
+    mp3_file
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let dir = temp_dir().join("presquile_copy_bench");
+    let mut group = c.benchmark_group("copy overhead");
+    for (name, in_place) in [("full copy", false), ("in place", true)] {
+        let options = ApplyOptions::default().with_in_place(in_place);
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || fresh_copy(&dir),
+                |mp3_file| {
+                    apply_with_options(
+                        black_box(test_file!("valid_chaps.cvs").into()),
+                        black_box(mp3_file),
+                        Mode::Sequential,
+                        options.clone(),
+                    )
+                },
+                BatchSize::PerIteration,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
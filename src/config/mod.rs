@@ -0,0 +1,59 @@
+use anyhow::Result;
+use id3::Version;
+use serde::{de::Error, Deserialize, Deserializer};
+use std::{fs::read_to_string, path::Path};
+
+use crate::{AppliersErrors, ApplyOptions, Mode};
+
+/// Looked up in the working directory when no config file is given
+pub const CONFIG_FILE: &str = "presquile.toml";
+
+/// Defaults read from a `presquile.toml` file, the command line flags win over them
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub suffix: Option<String>,
+    #[serde(default, deserialize_with = "id3_version")]
+    pub id3_version: Option<Version>,
+    pub mode: Option<Mode>,
+    pub lenient: Option<bool>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config> {
+        let read = || -> Result<Config> { Ok(toml::from_str(&read_to_string(path)?)?) };
+        read().map_err(|err| {
+            AppliersErrors::ConfigFile(format!("{}: {}", path.display(), err)).into()
+        })
+    }
+
+    /// Reads `path`, or `presquile.toml` in the working directory if there is one
+    pub fn discover(path: Option<&Path>) -> Result<Config> {
+        match path {
+            Some(path) => Config::load(path),
+            None if Path::new(CONFIG_FILE).is_file() => Config::load(Path::new(CONFIG_FILE)),
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// Fills in what `options` leaves unset with the values of this file
+    pub fn merge(&self, options: ApplyOptions) -> ApplyOptions {
+        ApplyOptions {
+            suffix: options.suffix.or_else(|| self.suffix.clone()),
+            id3_version: options.id3_version.or(self.id3_version),
+            lenient: options.lenient || self.lenient.unwrap_or_default(),
+            ..options
+        }
+    }
+}
+
+fn id3_version<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Version>, D::Error> {
+    match String::deserialize(deserializer)?.as_str() {
+        "2.3" => Ok(Some(Version::Id3v23)),
+        "2.4" => Ok(Some(Version::Id3v24)),
+        version => Err(D::Error::custom(format!(
+            "unsupported id3 version {}, expected 2.3 or 2.4",
+            version
+        ))),
+    }
+}
@@ -0,0 +1,38 @@
+use anyhow::Result;
+use csv::WriterBuilder;
+use id3::frame::Chapter;
+
+use super::title;
+use crate::format_time;
+
+/// Serializes chapters as a tab separated Audition marker file, the format `load_cvs` reads
+pub fn audition(chapters: &[Chapter]) -> Result<Vec<u8>> {
+    let mut writer = WriterBuilder::new()
+        .delimiter(b'\t')
+        .from_writer(Vec::new());
+    writer.write_record([
+        "Name",
+        "Start",
+        "Duration",
+        "Time Format",
+        "Type",
+        "Description",
+    ])?;
+    for chapter in chapters {
+        let description = chapter
+            .frames
+            .iter()
+            .find(|frame| frame.id() == "TIT3")
+            .and_then(|frame| frame.content().text())
+            .unwrap_or_default();
+        writer.write_record([
+            title(chapter),
+            &format_time(chapter.start_time),
+            &format_time(chapter.end_time.saturating_sub(chapter.start_time)),
+            "decimal",
+            "Cue",
+            description,
+        ])?;
+    }
+    Ok(writer.into_inner().map_err(|err| err.into_error())?)
+}
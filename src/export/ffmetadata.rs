@@ -0,0 +1,30 @@
+use id3::frame::Chapter;
+use std::fmt::Write;
+
+use super::title;
+
+/// Serializes chapters as an ffmpeg `;FFMETADATA1` file, in milliseconds
+pub fn ffmetadata(chapters: &[Chapter]) -> String {
+    let mut out = String::from(";FFMETADATA1\n");
+    for chapter in chapters {
+        let _ = write!(
+            out,
+            "[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+            chapter.start_time,
+            chapter.end_time,
+            escape(title(chapter))
+        );
+    }
+    out
+}
+
+//ffmpeg treats these as syntax unless they are backslash escaped
+fn escape(value: &str) -> String {
+    value.chars().fold(String::new(), |mut escaped, c| {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+        escaped
+    })
+}
@@ -0,0 +1,20 @@
+use id3::frame::Chapter;
+
+use crate::chapter_title;
+
+mod audition;
+mod ffmetadata;
+mod mp3chaps;
+mod podcast;
+mod webvtt;
+
+pub use audition::audition;
+pub use ffmetadata::ffmetadata;
+pub use mp3chaps::mp3chaps;
+pub use podcast::podcast_json;
+pub use webvtt::webvtt;
+
+/// Title of a chapter as exporters should print it
+fn title(chapter: &Chapter) -> &str {
+    chapter_title(chapter).trim()
+}
@@ -0,0 +1,20 @@
+use id3::frame::Chapter;
+use std::fmt::Write;
+
+use super::title;
+use crate::format_time;
+
+/// Serializes chapters as the `<name>.chapters.txt` lines read by mp3chaps, a start and a
+/// title per chapter
+pub fn mp3chaps(chapters: &[Chapter]) -> String {
+    let mut out = String::new();
+    for chapter in chapters {
+        let _ = writeln!(
+            out,
+            "{} {}",
+            format_time(chapter.start_time),
+            title(chapter)
+        );
+    }
+    out
+}
@@ -0,0 +1,49 @@
+use anyhow::Result;
+use id3::frame::Chapter;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use super::title;
+
+#[derive(Serialize)]
+struct PodcastChapters<'a> {
+    version: &'static str,
+    chapters: Vec<PodcastChapter<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PodcastChapter<'a> {
+    start_time: f64,
+    title: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    img: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<&'a str>,
+}
+
+/// Optional image and url of each chapter, keyed by `element_id`
+pub type Links = HashMap<String, (Option<String>, Option<String>)>;
+
+/// Serializes chapters as a Podcasting 2.0 `chapters.json`
+pub fn podcast_json(chapters: &[Chapter], links: &Links) -> Result<String> {
+    let chapters = chapters
+        .iter()
+        .map(|chapter| {
+            let (img, url) = links
+                .get(&chapter.element_id)
+                .map_or((None, None), |(img, url)| (img.as_deref(), url.as_deref()));
+            PodcastChapter {
+                start_time: f64::from(chapter.start_time) / 1000f64,
+                title: title(chapter),
+                img,
+                url,
+            }
+        })
+        .collect();
+    let json = serde_json::to_string_pretty(&PodcastChapters {
+        version: "1.2.0",
+        chapters,
+    })?;
+    Ok(json)
+}
@@ -0,0 +1,20 @@
+use id3::frame::Chapter;
+use std::fmt::Write;
+
+use super::title;
+use crate::format_time;
+
+/// Serializes chapters as `WEBVTT` cues, one per chapter
+pub fn webvtt(chapters: &[Chapter]) -> String {
+    let mut out = String::from("WEBVTT\n");
+    for chapter in chapters {
+        let _ = write!(
+            out,
+            "\n{} --> {}\n{}\n",
+            format_time(chapter.start_time),
+            format_time(chapter.end_time),
+            title(chapter)
+        );
+    }
+    out
+}
@@ -0,0 +1,54 @@
+use anyhow::{bail, ensure, Result};
+
+use crate::{format_time, AppliersErrors, AuditionCvsRecord};
+
+/// Audacity label tracks have no header, every line is `start<TAB>end<TAB>label` in seconds
+pub fn is_labels(content: &[u8]) -> bool {
+    let line = content
+        .split(|byte| *byte == b'\n')
+        .find(|line| !line.trim_ascii().is_empty());
+    let Some(line) = line.and_then(|line| std::str::from_utf8(line).ok()) else {
+        return false;
+    };
+    let mut fields = line.split('\t');
+    matches!(
+        (fields.next().map(seconds), fields.next().map(seconds)),
+        (Some(Ok(_)), Some(Ok(_)))
+    )
+}
+
+/// Parses a label line into its start in milliseconds and the equivalent Audition record
+pub fn parse_label(line: &str) -> Result<(u32, AuditionCvsRecord)> {
+    let mut fields = line.splitn(3, '\t');
+    let (Some(start), Some(end)) = (fields.next(), fields.next()) else {
+        bail!(AppliersErrors::ChaptersFileNotCompatible);
+    };
+    let (start, end) = (seconds(start)?, seconds(end)?);
+    ensure!(end >= start, AppliersErrors::ChaptersFileNotCompatible);
+
+    let record = AuditionCvsRecord {
+        name: fields.next().unwrap_or_default().trim().to_string(),
+        start: format_time(start),
+        //Point labels have no length, like Audition cue markers
+        duration: Some(format_time(end - start)),
+        description: None,
+        img: None,
+        image: None,
+        url: None,
+        id: None,
+        level: None,
+        marker_type: None,
+    };
+    Ok((start, record))
+}
+
+fn seconds(value: &str) -> Result<u32> {
+    let seconds = value.trim().parse::<f64>();
+    ensure!(
+        seconds
+            .as_ref()
+            .is_ok_and(|seconds| seconds.is_finite() && *seconds >= 0f64),
+        AppliersErrors::ChaptersFileNotCompatible
+    );
+    Ok((seconds.unwrap() * 1000f64).round() as u32)
+}
@@ -0,0 +1,5 @@
+mod audacity;
+mod youtube;
+
+pub use audacity::{is_labels, parse_label};
+pub use youtube::parse_line;
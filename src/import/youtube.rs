@@ -0,0 +1,27 @@
+use crate::AuditionCvsRecord;
+
+/// Parses a `[hh:]mm:ss Title` line, anything else is prose and yields `None`
+pub fn parse_line(line: &str) -> Option<AuditionCvsRecord> {
+    let (timestamp, name) = line.trim().split_once(char::is_whitespace)?;
+    let parts: Vec<_> = timestamp.split(':').collect();
+    let valid = (2..=3).contains(&parts.len())
+        && parts
+            .iter()
+            .all(|part| !part.is_empty() && part.bytes().all(|byte| byte.is_ascii_digit()));
+    if !valid || name.trim().is_empty() {
+        return None;
+    }
+
+    Some(AuditionCvsRecord {
+        name: name.trim().to_string(),
+        start: timestamp.to_string(),
+        duration: None,
+        description: None,
+        img: None,
+        image: None,
+        url: None,
+        id: None,
+        level: None,
+        marker_type: None,
+    })
+}
@@ -1,351 +1,5490 @@
 use anyhow::{bail, ensure, Result};
-use csv::ReaderBuilder;
+use csv::{Position, ReaderBuilder, StringRecord};
 use derive_new::new;
+use filetime::{set_file_mtime, FileTime};
+use glob::glob;
 use id3::{
-    frame::{Chapter, TableOfContents},
-    Frame, Tag, TagLike, Version,
+    frame::{ExtendedLink, Picture, PictureType},
+    no_tag_ok, Frame, Tag, TagLike,
 };
+use log::{debug, info, warn};
 use metadata::MediaFileMetadata;
-use model::AuditionCvsRecords;
-use std::{fs::copy, iter, path::PathBuf, thread};
+use rayon::{prelude::*, ThreadPoolBuilder};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{
+        copy, create_dir_all, metadata, read, read_dir, read_to_string, remove_file, rename,
+        set_permissions, write, File, OpenOptions,
+    },
+    io::{self, stdin, ErrorKind, Read, Write},
+    iter,
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 use thiserror::Error;
+use writer::{
+    ChapterWriter, FlacChapterWriter, Mp3ChapterWriter, Mp4ChapterWriter, OggChapterWriter, Part,
+    WavChapterWriter,
+};
 
+mod config;
+mod export;
+mod import;
 mod model;
+mod probe;
+mod writer;
 
+pub use config::{Config, CONFIG_FILE};
+pub use id3::{frame::Chapter, Version};
+pub use model::{AuditionCvsRecord, AuditionCvsRecords};
+use model::{KNOWN_LABELS, NAME_LABELS, START_LABELS};
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Mode {
     Sequential,
     Parallel,
 }
 
+#[derive(Debug, PartialEq)]
+enum AudioFormat {
+    Mp3,
+    Flac,
+    Mp4,
+    Wav,
+    Ogg,
+}
+
+impl AudioFormat {
+    /// Maps the `container_format` names reported by the metadata probe
+    fn from_container(container: &str) -> Option<AudioFormat> {
+        match container {
+            "MP3" => Some(AudioFormat::Mp3),
+            "FLAC" | "Raw FLAC" => Some(AudioFormat::Flac),
+            "Waveform Audio (WAV)" => Some(AudioFormat::Wav),
+            //Named after the extension, e.g. "MPEG-4 Part 14 (M4B)"
+            container if container.starts_with("MPEG-4 Part 14") => Some(AudioFormat::Mp4),
+            //Vorbis and Opus share the container, the writer tells them apart
+            "Ogg" => Some(AudioFormat::Ogg),
+            _ => None,
+        }
+    }
+
+    fn writer(&self, options: &ApplyOptions, parts: &[Part]) -> Box<dyn ChapterWriter> {
+        match self {
+            AudioFormat::Mp3 => Box::new(Mp3ChapterWriter {
+                version: options.id3_version.unwrap_or(Version::Id3v24),
+                toc_id: options.toc_id.as_deref().unwrap_or("toc").to_string(),
+                toc_top_level: options.toc_top_level.unwrap_or(true),
+                toc_ordered: options.toc_ordered.unwrap_or(true),
+                toc: options.toc.unwrap_or(true),
+                merge: options.merge,
+                chapter_id_prefix: options
+                    .chapter_id_prefix
+                    .as_deref()
+                    .unwrap_or(CHAPTER_ID_PREFIX)
+                    .to_string(),
+                parts: parts.to_vec(),
+                id3v1: options.id3v1,
+                byte_offsets: options.byte_offsets,
+            }),
+            AudioFormat::Flac => Box::new(FlacChapterWriter),
+            AudioFormat::Wav => Box::new(WavChapterWriter),
+            AudioFormat::Mp4 => Box::new(Mp4ChapterWriter),
+            AudioFormat::Ogg => Box::new(OggChapterWriter),
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum AppliersErrors {
-    #[error("Invalid audio file format {0}")]
-    AudioFileNotCompatible(String),
+    #[error("Can't add chapters to {0}, probed as {1}")]
+    AudioFileNotCompatible(String, String),
     #[error("Invalid chapter file format")]
     ChaptersFileNotCompatible,
+    #[error("Invalid chapter at line {0}: {1:?}")]
+    InvalidRow(u64, String),
+    #[error("Marker at row {0} starts at {1}, before the previous marker at {2}")]
+    UnsortedMarkers(usize, String, String),
+    #[error("Chapter \"{0}\" overlaps the following chapter \"{1}\"")]
+    OverlappingChapters(String, String),
+    #[error("Chapter \"{0}\" lasts {1}ms, less than the minimum allowed")]
+    ChapterTooShort(String, u32),
     #[error("Error while copying file")]
     CopyFile,
+    #[error("Output file {0} already exists")]
+    OutputExists(String),
     #[error("Thread has been interrupted")]
     ThreadInterrupted,
+    #[error("Can't embed image {1:?} in chapter \"{0}\"")]
+    ChapterImage(String, String),
+    #[error("No matching audio or chapter file found for {0}")]
+    MissingCounterpart(String),
+    #[error("Can't determine the duration of {0}")]
+    DurationUnknown(String),
+    #[error("Markers don't match the audio: {0}")]
+    MarkersSpan(String),
+    #[error("Both an output file and an output directory were given")]
+    OutputConflict,
+    #[error("Can't read the config file {0}")]
+    ConfigFile(String),
+    #[error("No file matches {0}")]
+    NoMatch(String),
+    #[error("Step {0} didn't finish in time")]
+    Timeout(String),
+    #[error("Chapter id {0:?} is used more than once")]
+    DuplicateId(String),
+    #[error("A single output file was given for {0} audio files")]
+    SharedOutput(usize),
+    #[error("{0} already has chapters")]
+    HasChapters(String),
+    #[error("{0} is newer than the audio and the markers, skipped")]
+    UpToDate(String),
+    #[error("{0} was given as both the markers and the audio file")]
+    SamePath(String),
+}
+
+impl AppliersErrors {
+    /// Process exit status the binary reports for this error, 1 being left to other failures
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AppliersErrors::AudioFileNotCompatible(..) => 2,
+            AppliersErrors::ChaptersFileNotCompatible => 3,
+            AppliersErrors::CopyFile => 4,
+            AppliersErrors::InvalidRow(..) => 5,
+            AppliersErrors::UnsortedMarkers(..) => 6,
+            AppliersErrors::OverlappingChapters(..) => 7,
+            AppliersErrors::ChapterTooShort(..) => 8,
+            AppliersErrors::OutputExists(_) => 9,
+            AppliersErrors::ThreadInterrupted => 10,
+            AppliersErrors::ChapterImage(..) => 11,
+            AppliersErrors::MissingCounterpart(_) => 12,
+            AppliersErrors::DurationUnknown(_) => 13,
+            AppliersErrors::MarkersSpan(_) => 14,
+            AppliersErrors::OutputConflict => 15,
+            AppliersErrors::ConfigFile(_) => 16,
+            AppliersErrors::NoMatch(_) => 17,
+            AppliersErrors::Timeout(_) => 18,
+            AppliersErrors::DuplicateId(_) => 19,
+            AppliersErrors::SharedOutput(_) => 20,
+            AppliersErrors::HasChapters(_) => 21,
+            AppliersErrors::UpToDate(_) => 22,
+            AppliersErrors::SamePath(_) => 23,
+        }
+    }
+
+    /// Name of the variant, for machine readable error reports
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppliersErrors::AudioFileNotCompatible(..) => "AudioFileNotCompatible",
+            AppliersErrors::ChaptersFileNotCompatible => "ChaptersFileNotCompatible",
+            AppliersErrors::CopyFile => "CopyFile",
+            AppliersErrors::InvalidRow(..) => "InvalidRow",
+            AppliersErrors::UnsortedMarkers(..) => "UnsortedMarkers",
+            AppliersErrors::OverlappingChapters(..) => "OverlappingChapters",
+            AppliersErrors::ChapterTooShort(..) => "ChapterTooShort",
+            AppliersErrors::OutputExists(_) => "OutputExists",
+            AppliersErrors::ThreadInterrupted => "ThreadInterrupted",
+            AppliersErrors::ChapterImage(..) => "ChapterImage",
+            AppliersErrors::MissingCounterpart(_) => "MissingCounterpart",
+            AppliersErrors::DurationUnknown(_) => "DurationUnknown",
+            AppliersErrors::MarkersSpan(_) => "MarkersSpan",
+            AppliersErrors::OutputConflict => "OutputConflict",
+            AppliersErrors::ConfigFile(_) => "ConfigFile",
+            AppliersErrors::NoMatch(_) => "NoMatch",
+            AppliersErrors::Timeout(_) => "Timeout",
+            AppliersErrors::DuplicateId(_) => "DuplicateId",
+            AppliersErrors::SharedOutput(_) => "SharedOutput",
+            AppliersErrors::HasChapters(_) => "HasChapters",
+            AppliersErrors::UpToDate(_) => "UpToDate",
+            AppliersErrors::SamePath(_) => "SamePath",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ApplyOptions {
+    /// Destination of the enriched file, next to the source with `suffix` appended if unset
+    pub output: Option<PathBuf>,
+    /// Appended to the source file stem to name the output, `_enriched` if unset
+    pub suffix: Option<String>,
+    /// Directory the output is written to instead of next to the source, created if missing
+    pub output_dir: Option<PathBuf>,
+    /// Reject markers that are not in chronological order instead of sorting them
+    pub strict_order: bool,
+    /// Chapters shorter than this many milliseconds are rejected, `0` accepts everything
+    pub min_chapter_duration: u32,
+    /// Fold chapters shorter than `min_chapter_duration` into a neighbour instead of failing
+    pub merge_short_chapters: bool,
+    /// End every chapter where the next one starts, ignoring the `Duration` column
+    pub gapless: bool,
+    /// Milliseconds added to every marker start, negative to move them earlier, for audio
+    /// mastered with more or less leader than the edit the markers come from
+    pub start_offset_ms: Option<i64>,
+    /// Warn when the last chapter lasts less than this fraction of the audio, 1% if unset
+    pub min_last_chapter_ratio: Option<f64>,
+    /// Fail instead of warning when the markers start late, end too early or past the end of
+    /// the audio
+    pub strict_span: bool,
+    /// Skip CSV rows that fail to parse, with a warning, as long as one valid row remains
+    pub lenient: bool,
+    /// Warn about the CSV columns that are not read into the chapters
+    pub strict_columns: bool,
+    /// Column names of a CSV file without a header row, in order (e.g. `["start", "name"]`)
+    pub columns: Option<Vec<String>>,
+    /// Header of the column holding the chapter titles, in place of `Name` and its translations
+    pub name_column: Option<String>,
+    /// Header of the column holding the marker starts, in place of `Start` and its translations
+    pub start_column: Option<String>,
+    /// Only turn markers of this `Type` (e.g. `Cue`) into chapters, every row is used if unset
+    pub marker_type: Option<String>,
+    /// ID3 version of the tag written to mp3 files, v2.4 if unset
+    pub id3_version: Option<Version>,
+    /// Also write an ID3v1 tag to mp3 files, titled like the table of contents
+    pub id3v1: bool,
+    /// Prepended to the chapters' zero padded element ids, `"chp"` if unset
+    pub chapter_id_prefix: Option<String>,
+    /// Markers without a name are titled this followed by their position, `"Chapter"` if unset
+    pub untitled_prefix: Option<String>,
+    /// Title of a chapter added from the start of the audio up to a first marker placed later
+    pub intro_title: Option<String>,
+    /// Trim the titles, collapse their runs of whitespace and drop their control characters
+    pub normalize_titles: bool,
+    /// Warn about the titles longer than this many characters, some players truncate them
+    pub max_title_len: Option<usize>,
+    /// Add the chapters to the ones already in the mp3 instead of replacing them
+    pub merge: bool,
+    /// Title of the mp3 table of contents, the album or `"Chapters"` if unset
+    pub toc_title: Option<String>,
+    /// Element id of the mp3 table of contents, `"toc"` if unset
+    pub toc_id: Option<String>,
+    /// Mark the mp3 table of contents as the root one, `true` if unset
+    pub toc_top_level: Option<bool>,
+    /// Mark the mp3 chapters as meant to be played in order, `true` if unset
+    pub toc_ordered: Option<bool>,
+    /// Write a table of contents next to the mp3 chapters, `true` if unset
+    pub toc: Option<bool>,
+    /// Longest wait for each step of the parallel mode, run on detached threads when set
+    /// so a hung one can be given up on. The copy then reports no progress
+    pub step_timeout: Option<Duration>,
+    /// Copy the source again this many times after a failed attempt, for network mounts whose
+    /// reads fail now and then
+    pub copy_retries: u32,
+    /// Wait before the first retry of the copy, doubled before each of the next ones, half a
+    /// second if unset
+    pub copy_retry_delay: Option<Duration>,
+    /// Print the chapters and the destination without copying or writing anything
+    pub dry_run: bool,
+    /// Leave the audio alone and write the chapters to a `<name>.chapters.txt` next to it,
+    /// in the format of mp3chaps. `output` names that file instead when set
+    pub sidecar: bool,
+    /// Replace the source with the enriched file instead of writing next to it, `output` is
+    /// ignored. The source is still copied first, a failed write leaves it untouched
+    pub in_place: bool,
+    /// Give the output the modification time of the source rather than a fresh one
+    pub preserve_mtime: bool,
+    /// Fill the chapter byte offsets of constant bitrate mp3 files, "not used" for others
+    pub byte_offsets: bool,
+    /// Keep a `.bak` copy of the source before writing in place
+    pub backup: bool,
+    /// Fail instead of replacing an output file that already exists
+    pub keep_existing: bool,
+    /// Fail instead of replacing the chapters already in an mp3 file, unless merging
+    pub keep_chapters: bool,
+    /// Skip the files whose output is at least as recent as both the audio and the markers,
+    /// with an [`AppliersErrors::UpToDate`] in place of the output
+    pub incremental: bool,
+}
+
+/// Chainable setters, e.g. `ApplyOptions::default().with_lenient(true).with_suffix("_chapters")`
+impl ApplyOptions {
+    pub fn with_output(mut self, output: impl Into<PathBuf>) -> Self {
+        self.output = Some(output.into());
+        self
+    }
+
+    pub fn with_suffix(mut self, suffix: impl Into<String>) -> Self {
+        self.suffix = Some(suffix.into());
+        self
+    }
+
+    pub fn with_output_dir(mut self, output_dir: impl Into<PathBuf>) -> Self {
+        self.output_dir = Some(output_dir.into());
+        self
+    }
+
+    pub fn with_strict_order(mut self, strict_order: bool) -> Self {
+        self.strict_order = strict_order;
+        self
+    }
+
+    pub fn with_min_chapter_duration(mut self, min_chapter_duration: u32) -> Self {
+        self.min_chapter_duration = min_chapter_duration;
+        self
+    }
+
+    pub fn with_merge_short_chapters(mut self, merge_short_chapters: bool) -> Self {
+        self.merge_short_chapters = merge_short_chapters;
+        self
+    }
+
+    pub fn with_gapless(mut self, gapless: bool) -> Self {
+        self.gapless = gapless;
+        self
+    }
+
+    pub fn with_start_offset_ms(mut self, start_offset_ms: i64) -> Self {
+        self.start_offset_ms = Some(start_offset_ms);
+        self
+    }
+
+    pub fn with_min_last_chapter_ratio(mut self, min_last_chapter_ratio: f64) -> Self {
+        self.min_last_chapter_ratio = Some(min_last_chapter_ratio);
+        self
+    }
+
+    pub fn with_strict_span(mut self, strict_span: bool) -> Self {
+        self.strict_span = strict_span;
+        self
+    }
+
+    pub fn with_strict_columns(mut self, strict_columns: bool) -> Self {
+        self.strict_columns = strict_columns;
+        self
+    }
+
+    pub fn with_intro_title(mut self, intro_title: impl Into<String>) -> Self {
+        self.intro_title = Some(intro_title.into());
+        self
+    }
+
+    pub fn with_normalize_titles(mut self, normalize_titles: bool) -> Self {
+        self.normalize_titles = normalize_titles;
+        self
+    }
+
+    pub fn with_max_title_len(mut self, max_title_len: usize) -> Self {
+        self.max_title_len = Some(max_title_len);
+        self
+    }
+
+    pub fn with_lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    pub fn with_columns(mut self, columns: Vec<String>) -> Self {
+        self.columns = Some(columns);
+        self
+    }
+
+    pub fn with_name_column(mut self, name_column: impl Into<String>) -> Self {
+        self.name_column = Some(name_column.into());
+        self
+    }
+
+    pub fn with_start_column(mut self, start_column: impl Into<String>) -> Self {
+        self.start_column = Some(start_column.into());
+        self
+    }
+
+    pub fn with_marker_type(mut self, marker_type: impl Into<String>) -> Self {
+        self.marker_type = Some(marker_type.into());
+        self
+    }
+
+    pub fn with_id3_version(mut self, id3_version: Version) -> Self {
+        self.id3_version = Some(id3_version);
+        self
+    }
+
+    pub fn with_id3v1(mut self, id3v1: bool) -> Self {
+        self.id3v1 = id3v1;
+        self
+    }
+
+    pub fn with_chapter_id_prefix(mut self, chapter_id_prefix: impl Into<String>) -> Self {
+        self.chapter_id_prefix = Some(chapter_id_prefix.into());
+        self
+    }
+
+    pub fn with_untitled_prefix(mut self, untitled_prefix: impl Into<String>) -> Self {
+        self.untitled_prefix = Some(untitled_prefix.into());
+        self
+    }
+
+    pub fn with_merge(mut self, merge: bool) -> Self {
+        self.merge = merge;
+        self
+    }
+
+    pub fn with_toc_title(mut self, toc_title: impl Into<String>) -> Self {
+        self.toc_title = Some(toc_title.into());
+        self
+    }
+
+    pub fn with_toc_id(mut self, toc_id: impl Into<String>) -> Self {
+        self.toc_id = Some(toc_id.into());
+        self
+    }
+
+    pub fn with_toc_top_level(mut self, toc_top_level: bool) -> Self {
+        self.toc_top_level = Some(toc_top_level);
+        self
+    }
+
+    pub fn with_toc_ordered(mut self, toc_ordered: bool) -> Self {
+        self.toc_ordered = Some(toc_ordered);
+        self
+    }
+
+    pub fn with_toc(mut self, toc: bool) -> Self {
+        self.toc = Some(toc);
+        self
+    }
+
+    pub fn with_step_timeout(mut self, step_timeout: Duration) -> Self {
+        self.step_timeout = Some(step_timeout);
+        self
+    }
+
+    pub fn with_copy_retries(mut self, copy_retries: u32) -> Self {
+        self.copy_retries = copy_retries;
+        self
+    }
+
+    pub fn with_copy_retry_delay(mut self, copy_retry_delay: Duration) -> Self {
+        self.copy_retry_delay = Some(copy_retry_delay);
+        self
+    }
+
+    pub fn with_incremental(mut self, incremental: bool) -> Self {
+        self.incremental = incremental;
+        self
+    }
+
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn with_sidecar(mut self, sidecar: bool) -> Self {
+        self.sidecar = sidecar;
+        self
+    }
+
+    pub fn with_preserve_mtime(mut self, preserve_mtime: bool) -> Self {
+        self.preserve_mtime = preserve_mtime;
+        self
+    }
+
+    pub fn with_byte_offsets(mut self, byte_offsets: bool) -> Self {
+        self.byte_offsets = byte_offsets;
+        self
+    }
+
+    pub fn with_in_place(mut self, in_place: bool) -> Self {
+        self.in_place = in_place;
+        self
+    }
+
+    pub fn with_backup(mut self, backup: bool) -> Self {
+        self.backup = backup;
+        self
+    }
+
+    pub fn with_keep_existing(mut self, keep_existing: bool) -> Self {
+        self.keep_existing = keep_existing;
+        self
+    }
+
+    pub fn with_keep_chapters(mut self, keep_chapters: bool) -> Self {
+        self.keep_chapters = keep_chapters;
+        self
+    }
+}
+
+/// Issues that don't stop an apply, collected in [`ApplyReport::warnings`] and
+/// [`VerifyReport::warnings`] for callers to match on
+#[derive(Debug, Clone, PartialEq, Error, Serialize)]
+#[serde(tag = "kind")]
+pub enum Warning {
+    #[error("Skipping row {line}: {row:?}")]
+    SkippedBadRow { line: u64, row: String },
+    #[error("Unexpected column \"{column}\"")]
+    UnexpectedColumn { column: String },
+    #[error("Dropping marker \"{title}\": starts at {at} like \"{previous}\"", at = format_time(*.start))]
+    DroppedZeroLength {
+        title: String,
+        start: u32,
+        previous: String,
+    },
+    #[error(
+        "Dropping chapter \"{title}\": starts at {at}, after the end of the audio at {end}",
+        at = format_time(*.start),
+        end = format_time(*.audio_end)
+    )]
+    DroppedPastEnd {
+        title: String,
+        start: u32,
+        audio_end: u32,
+    },
+    #[error(
+        "Clamping chapter \"{title}\": ends at {at}, after the end of the audio at {end}",
+        at = format_time(*.end_time),
+        end = format_time(*.audio_end)
+    )]
+    ClampedToDuration {
+        title: String,
+        end_time: u32,
+        audio_end: u32,
+    },
+    #[error(
+        "The last chapter \"{title}\" lasts {lasts}, less than {percent}% of the audio",
+        lasts = format_time(*.length),
+        percent = .ratio * 100f64
+    )]
+    ShortLastChapter {
+        title: String,
+        length: u32,
+        ratio: f64,
+    },
+    #[error("Chapter \"{title}\" has a title of {len} characters, more than {max_len}")]
+    LongTitle {
+        title: String,
+        len: usize,
+        max_len: usize,
+    },
+    #[error("Chapter \"{title}\" at {at} has zero length", at = format_time(*.start))]
+    ZeroLength { title: String, start: u32 },
+    #[error("Dropping marker \"{title}\": moved before the start of the audio")]
+    DroppedBeforeStart { title: String },
+    #[error(
+        "The first chapter \"{title}\" starts at {at}, the opening has no chapter",
+        at = format_time(*.start)
+    )]
+    LateFirstChapter { title: String, start: u32 },
+}
+
+/// What [`apply_reported`] wrote, for callers that need more than the destination
+#[derive(Debug, Serialize)]
+pub struct ApplyReport {
+    pub output: PathBuf,
+    /// Container of the source as reported by the metadata probe, e.g. `"MP3"` or `"Ogg"`
+    pub container_format: String,
+    pub chapter_count: usize,
+    /// Sum of the chapters' lengths in milliseconds
+    pub total_duration_ms: u32,
+    /// CSV rows skipped in lenient mode and chapters clamped or dropped on the way to the output
+    pub warnings: Vec<Warning>,
+    /// What a dry run would write, one `id<TAB>start --> end<TAB>title` line a chapter
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub preview: Vec<String>,
+}
+
+impl ApplyReport {
+    fn new(
+        output: PathBuf,
+        container_format: String,
+        chapters: &[Chapter],
+        warnings: Vec<Warning>,
+    ) -> Self {
+        ApplyReport {
+            output,
+            container_format,
+            chapter_count: chapters.len(),
+            total_duration_ms: chapters
+                .iter()
+                .map(|chapter| chapter.end_time - chapter.start_time)
+                .sum(),
+            warnings,
+            preview: Vec::new(),
+        }
+    }
+}
+
+/// Writes the markers of `audition_cvs`, `-` for the standard input, to a copy of `mp3_file`
+pub fn apply(audition_cvs: PathBuf, mp3_file: PathBuf, parallel: Mode) -> Result<PathBuf> {
+    apply_with_options(audition_cvs, mp3_file, parallel, ApplyOptions::default())
+}
+
+/// [`apply`] for async callers, run on tokio's blocking pool so the runtime threads stay free
+#[cfg(feature = "tokio")]
+pub async fn apply_async(
+    audition_cvs: PathBuf,
+    mp3_file: PathBuf,
+    parallel: Mode,
+) -> Result<PathBuf> {
+    tokio::task::spawn_blocking(move || apply(audition_cvs, mp3_file, parallel)).await?
+}
+
+pub fn apply_with_output(
+    audition_cvs: PathBuf,
+    mp3_file: PathBuf,
+    output: PathBuf,
+    parallel: Mode,
+) -> Result<PathBuf> {
+    let options = ApplyOptions::default().with_output(output);
+    apply_with_options(audition_cvs, mp3_file, parallel, options)
+}
+
+pub fn apply_with_options(
+    audition_cvs: PathBuf,
+    mp3_file: PathBuf,
+    parallel: Mode,
+    options: ApplyOptions,
+) -> Result<PathBuf> {
+    apply_reported(audition_cvs, mp3_file, parallel, options).map(|report| report.output)
+}
+
+pub fn apply_reported(
+    audition_cvs: PathBuf,
+    mp3_file: PathBuf,
+    parallel: Mode,
+    options: ApplyOptions,
+) -> Result<ApplyReport> {
+    run(audition_cvs, mp3_file, parallel, options, None)
+}
+
+/// Same as [`apply_with_options`] for every variant of an episode, such as its 64k and 128k
+/// encodes, sharing one marker file. Each is measured on its own
+pub fn apply_variants(
+    audition_cvs: PathBuf,
+    mp3_files: Vec<PathBuf>,
+    parallel: Mode,
+    options: ApplyOptions,
+) -> Result<Vec<PathBuf>> {
+    ensure!(
+        mp3_files.len() < 2 || options.output.is_none(),
+        AppliersErrors::SharedOutput(mp3_files.len())
+    );
+    mp3_files
+        .into_iter()
+        .map(|mp3_file| {
+            apply_with_options(audition_cvs.clone(), mp3_file, parallel, options.clone())
+        })
+        .collect()
+}
+
+/// Bytes copied so far and total size of the file being copied
+pub type CopyProgress<'a> = &'a (dyn Fn(u64, u64) + Sync);
+
+/// Same as [`apply_reported`], calling `progress` as the staged copy of the audio file grows
+pub fn apply_with_progress(
+    audition_cvs: PathBuf,
+    mp3_file: PathBuf,
+    parallel: Mode,
+    options: ApplyOptions,
+    progress: impl Fn(u64, u64) + Sync,
+) -> Result<ApplyReport> {
+    run(audition_cvs, mp3_file, parallel, options, Some(&progress))
+}
+
+fn run(
+    audition_cvs: PathBuf,
+    mp3_file: PathBuf,
+    parallel: Mode,
+    options: ApplyOptions,
+    progress: Option<CopyProgress>,
+) -> Result<ApplyReport> {
+    //A typo away, and the audio would only fail as a markers file once copied
+    ensure!(
+        !same_file(&audition_cvs, &mp3_file),
+        AppliersErrors::SamePath(mp3_file.display().to_string())
+    );
+    let output = output_path(&mp3_file, &options)?;
+    ensure!(
+        !options.incremental
+            || output == mp3_file
+            || !up_to_date(&output, &[&audition_cvs, &mp3_file]),
+        AppliersErrors::UpToDate(output.display().to_string())
+    );
+    //Updating the source itself is what in place mode and re-runs ask for
+    ensure!(
+        !options.keep_existing || output == mp3_file || !output.exists(),
+        AppliersErrors::OutputExists(output.display().to_string())
+    );
+    info!(
+        "Applying {:?} to {:?} in {:?} mode, writing {:?}",
+        audition_cvs, mp3_file, parallel, output
+    );
+    let applier = Applier::new(audition_cvs, mp3_file, output, options, progress);
+    match parallel {
+        Mode::Sequential => applier.apply_seq(),
+        Mode::Parallel => applier.apply_parallel(),
+    }
+}
+
+/// Where `options` sends what is made of `mp3_file`: the source itself in place mode, `output`,
+/// or the enriched name (the sidecar one) next to it or in `output_dir`
+fn output_path(mp3_file: &Path, options: &ApplyOptions) -> Result<PathBuf> {
+    ensure!(
+        options.output.is_none() || options.output_dir.is_none(),
+        AppliersErrors::OutputConflict
+    );
+    let output = match &options.output {
+        //The sidecar is never written over the audio
+        _ if options.in_place && !options.sidecar => mp3_file.to_path_buf(),
+        Some(output) => output.clone(),
+        None => {
+            let output = match options.sidecar {
+                true => mp3_file.with_extension(SIDECAR_EXTENSION),
+                false => enriched_path(mp3_file, options.suffix.as_deref().unwrap_or(SUFFIX))?,
+            };
+            match (&options.output_dir, output.file_name()) {
+                (Some(dir), Some(file_name)) => dir.join(file_name),
+                _ => output,
+            }
+        }
+    };
+    Ok(output)
+}
+
+/// Whether both paths lead to the same file, however they are spelled
+fn same_file(first: &Path, second: &Path) -> bool {
+    match (first.canonicalize(), second.canonicalize()) {
+        (Ok(first), Ok(second)) => first == second,
+        _ => first == second,
+    }
+}
+
+/// Whether `output` exists and was modified no earlier than any of `inputs`
+fn up_to_date(output: &Path, inputs: &[&Path]) -> bool {
+    let modified = |path: &Path| metadata(path).and_then(|metadata| metadata.modified()).ok();
+    modified(output).is_some_and(|output| {
+        inputs
+            .iter()
+            .all(|input| modified(input).is_some_and(|input| input <= output))
+    })
+}
+
+/// Result of every file found by [`batch`], keyed by the audio file (or the orphan marker file)
+pub type BatchResults = Vec<(PathBuf, Result<PathBuf>)>;
+
+const AUDIO_EXTENSIONS: [&str; 7] = ["mp3", "flac", "m4a", "m4b", "wav", "ogg", "opus"];
+const CHAPTERS_EXTENSIONS: [&str; 2] = ["cvs", "csv"];
+
+/// Applies every marker file in `dir` to the audio file sharing its stem.
+/// Files without a counterpart are reported in the results, they don't stop the batch
+pub fn batch(dir: PathBuf, parallel: Mode) -> Result<BatchResults> {
+    batch_with_progress(dir, parallel, |_, _| {})
+}
+
+/// Same as [`batch`], calling `progress` as soon as each file is done, in completion order
+pub fn batch_with_progress(
+    dir: PathBuf,
+    parallel: Mode,
+    progress: impl FnMut(&Path, &Result<PathBuf>),
+) -> Result<BatchResults> {
+    batch_with_options(dir, parallel, &ApplyOptions::default(), progress)
+}
+
+/// Same as [`batch_with_progress`], applying every file with `options`
+pub fn batch_with_options(
+    dir: PathBuf,
+    parallel: Mode,
+    options: &ApplyOptions,
+    progress: impl FnMut(&Path, &Result<PathBuf>),
+) -> Result<BatchResults> {
+    let files = batch_files(&dir)?;
+    run_batch(files, parallel, options, progress)
+}
+
+/// Same as [`batch_with_options`] for the audio files matching `pattern`, such as
+/// `episodes/*.mp3`, each with the marker file sharing its stem next to it
+pub fn batch_glob(
+    pattern: &str,
+    parallel: Mode,
+    options: &ApplyOptions,
+    progress: impl FnMut(&Path, &Result<PathBuf>),
+) -> Result<BatchResults> {
+    let files = glob_files(pattern)?;
+    run_batch(files, parallel, options, progress)
+}
+
+fn run_batch(
+    (pairs, orphans): (Vec<BatchPair>, Vec<PathBuf>),
+    parallel: Mode,
+    options: &ApplyOptions,
+    mut progress: impl FnMut(&Path, &Result<PathBuf>),
+) -> Result<BatchResults> {
+    let apply = |chapters_file, audio_file| {
+        apply_with_options(chapters_file, audio_file, Mode::Sequential, options.clone())
+    };
+    let mut results = BatchResults::new();
+    let mut report = |file: PathBuf, output: Result<PathBuf>| {
+        progress(&file, &output);
+        results.push((file, output));
+    };
+    for (file, output) in orphans.into_iter().map(missing_counterpart) {
+        report(file, output);
+    }
+
+    match parallel {
+        Mode::Sequential => {
+            for (chapters_file, audio_file) in pairs {
+                let output = apply(chapters_file, audio_file.clone());
+                report(audio_file, output);
+            }
+        }
+        Mode::Parallel => thread::scope(|s| {
+            let (sender, receiver) = mpsc::channel();
+            let handles: Vec<_> = pairs
+                .into_iter()
+                .map(|(chapters_file, audio_file)| {
+                    let sender = sender.clone();
+                    let source = audio_file.clone();
+                    let apply = &apply;
+                    let handle = s.spawn(move || {
+                        let output = apply(chapters_file, source.clone());
+                        //The receiver lives until the scope ends, sending can't fail
+                        let _ = sender.send((source, output));
+                    });
+                    (audio_file, handle)
+                })
+                .collect();
+            //Only the workers hold a sender now, the loop ends once they are all done
+            drop(sender);
+            for (audio_file, output) in receiver {
+                report(audio_file, output);
+            }
+            for (audio_file, handle) in handles {
+                if handle.join().is_err() {
+                    report(audio_file, Err(AppliersErrors::ThreadInterrupted.into()));
+                }
+            }
+        }),
+    }
+
+    results.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(results)
+}
+
+/// Applies the marker files of `dir` like [`batch`], several files at a time on a pool
+/// of `threads` workers, as many as the available cores if 0
+pub fn batch_parallel(dir: PathBuf, threads: usize) -> Result<BatchResults> {
+    let (pairs, orphans) = batch_files(&dir)?;
+    let pool = ThreadPoolBuilder::new().num_threads(threads).build()?;
+    let mut results: BatchResults = pool.install(|| {
+        pairs
+            .into_par_iter()
+            .map(|(chapters_file, audio_file)| {
+                let output = apply(chapters_file, audio_file.clone(), Mode::Sequential);
+                (audio_file, output)
+            })
+            .collect()
+    });
+    results.extend(orphans.into_iter().map(missing_counterpart));
+
+    results.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(results)
+}
+
+/// Marker file and the audio file it applies to
+type BatchPair = (PathBuf, PathBuf);
+
+/// Pairs the marker files of `dir` with their audio file, along with the files left alone
+fn batch_files(dir: &Path) -> Result<(Vec<BatchPair>, Vec<PathBuf>)> {
+    let mut paths = Vec::new();
+    for entry in read_dir(dir)? {
+        paths.push(entry?.path());
+    }
+    Ok(pair_files(paths))
+}
+
+/// Replaces the patterns among `files`, such as `episodes/*.mp3`, with the files they match,
+/// for the shells that leave them alone. Existing files are kept as they are even if their name
+/// looks like a pattern
+pub fn expand_patterns(files: Vec<PathBuf>) -> Result<Vec<PathBuf>> {
+    let mut expanded = Vec::new();
+    for file in files {
+        let pattern = file.to_str().filter(|_| !file.exists());
+        match pattern {
+            Some(pattern) if pattern.contains(['*', '?', '[']) => {
+                let mut paths = glob(pattern)?.collect::<Result<Vec<_>, _>>()?;
+                ensure!(
+                    !paths.is_empty(),
+                    AppliersErrors::NoMatch(pattern.to_string())
+                );
+                paths.sort();
+                expanded.extend(paths);
+            }
+            _ => expanded.push(file),
+        }
+    }
+    Ok(expanded)
+}
+
+/// Pairs the files matching `pattern` like [`batch_files`], looking for the marker files
+/// of the matched audio files next to them
+fn glob_files(pattern: &str) -> Result<(Vec<BatchPair>, Vec<PathBuf>)> {
+    let mut paths = glob(pattern)?.collect::<Result<Vec<_>, _>>()?;
+    ensure!(
+        !paths.is_empty(),
+        AppliersErrors::NoMatch(pattern.to_string())
+    );
+    let siblings: Vec<_> = paths
+        .iter()
+        .flat_map(|path| {
+            CHAPTERS_EXTENSIONS
+                .iter()
+                .map(|extension| path.with_extension(extension))
+        })
+        .filter(|sibling| sibling.is_file())
+        .collect();
+    paths.extend(siblings);
+    paths.sort();
+    paths.dedup();
+    Ok(pair_files(paths))
+}
+
+fn pair_files(paths: Vec<PathBuf>) -> (Vec<BatchPair>, Vec<PathBuf>) {
+    let mut audio_files = Vec::new();
+    let mut chapters_files = HashMap::new();
+    for path in paths {
+        let (Some(stem), Some(extension)) = (
+            path.file_stem().and_then(|stem| stem.to_str()),
+            path.extension().and_then(|extension| extension.to_str()),
+        ) else {
+            continue;
+        };
+        //Skip staged files and the outputs of a previous run
+        if !path.is_file() || stem.starts_with('.') || stem.ends_with(SUFFIX) {
+            continue;
+        }
+        let extension = extension.to_lowercase();
+        if AUDIO_EXTENSIONS.contains(&extension.as_str()) {
+            audio_files.push((stem.to_string(), path));
+        } else if CHAPTERS_EXTENSIONS.contains(&extension.as_str()) {
+            chapters_files.insert(stem.to_string(), path);
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let mut orphans = Vec::new();
+    for (stem, audio_file) in audio_files {
+        match chapters_files.remove(&stem) {
+            Some(chapters_file) => pairs.push((chapters_file, audio_file)),
+            None => orphans.push(audio_file),
+        }
+    }
+    orphans.extend(chapters_files.into_values());
+    (pairs, orphans)
+}
+
+fn missing_counterpart(file: PathBuf) -> (PathBuf, Result<PathBuf>) {
+    let error = AppliersErrors::MissingCounterpart(file.display().to_string());
+    (file, Err(error.into()))
+}
+
+/// Summary of a marker file checked by [`verify`]
+#[derive(Debug)]
+pub struct VerifyReport {
+    pub chapters: usize,
+    /// Milliseconds between the first marker and the end of the last one,
+    /// open ended last markers count up to their start
+    pub span: u32,
+    pub warnings: Vec<Warning>,
+}
+
+/// Runs the same parsing and validations as [`apply_with_options`] on the CSV alone,
+/// nothing is read from or written to any audio file
+pub fn verify(audition_cvs: PathBuf, options: &ApplyOptions) -> Result<VerifyReport> {
+    //Without audio there is no duration: the last chapter is left open ended
+    let mut warnings = Vec::new();
+    let mut records = load_cvs_reporting(&audition_cvs, options, &mut warnings)?;
+    split_parts(&mut records)?;
+    let chapters = process_chapters(records, OPEN_END as f64, options, &mut warnings)?;
+    let first = chapters.first().map_or(0, |chapter| chapter.start_time);
+    let last = chapters.last().map_or(0, |chapter| match chapter.end_time {
+        OPEN_END => chapter.start_time,
+        end_time => end_time,
+    });
+    warnings.extend(
+        chapters
+            .iter()
+            .filter(|chapter| chapter.start_time == chapter.end_time)
+            .map(|chapter| Warning::ZeroLength {
+                title: chapter_title(chapter).to_string(),
+                start: chapter.start_time,
+            }),
+    );
+    Ok(VerifyReport {
+        chapters: chapters.len(),
+        span: last - first,
+        warnings,
+    })
+}
+
+const OPEN_END: u32 = u32::MAX;
+
+/// Parses an Audition CSV into chapters spanning `duration_ms`, without touching any audio file
+pub fn parse_chapters(csv: PathBuf, duration_ms: f64) -> Result<Vec<Chapter>> {
+    build_chapters(load_cvs(&csv, &ApplyOptions::default())?, duration_ms)
+}
+
+/// Writes the chapters embedded in an mp3 file as an Audition CSV, to be edited and applied again
+pub fn extract(mp3_file: PathBuf, out: PathBuf) -> Result<()> {
+    let tag = Tag::read_from_path(mp3_file)?;
+    let mut chapters: Vec<_> = tag.chapters().cloned().collect();
+    chapters.sort_by_key(|chapter| chapter.start_time);
+    write(out, export::audition(&chapters)?)?;
+    Ok(())
+}
+
+/// Writes the chapters of an Audition CSV spanning `duration_ms` as an ffmpeg metadata file
+pub fn export_ffmetadata(csv: PathBuf, duration_ms: f64, out: PathBuf) -> Result<()> {
+    let chapters = parse_chapters(csv, duration_ms)?;
+    write(out, export::ffmetadata(&chapters))?;
+    Ok(())
+}
+
+/// Writes the chapters of an Audition CSV spanning `duration_ms` as WebVTT cues
+pub fn export_webvtt(csv: PathBuf, duration_ms: f64, out: PathBuf) -> Result<()> {
+    let chapters = parse_chapters(csv, duration_ms)?;
+    write(out, export::webvtt(&chapters))?;
+    Ok(())
+}
+
+/// Writes the chapters of an Audition CSV spanning `duration_ms` as Podcasting 2.0 JSON,
+/// filling `img` and `url` from the optional `Img` and `Url` columns
+pub fn export_podcast_json(csv: PathBuf, duration_ms: f64, out: PathBuf) -> Result<()> {
+    let records = load_cvs(&csv, &ApplyOptions::default())?;
+    let count = records.len();
+    let links = records
+        .iter()
+        .enumerate()
+        .map(|(idx, record)| {
+            let id = element_id(record, CHAPTER_ID_PREFIX, idx, count);
+            (id, (record.img.clone(), record.url.clone()))
+        })
+        .collect();
+    let chapters = build_chapters(records, duration_ms)?;
+    write(out, export::podcast_json(&chapters, &links)?)?;
+    Ok(())
+}
+
+/// Converts an Audition `[[hh:]mm:]ss[.mmm]` timestamp into milliseconds, `,` is accepted as
+/// decimal separator too. Without colons it reads as plain seconds, e.g. `93.5` or `3600`
+pub fn convert_time(time: &str) -> Result<u32> {
+    use crate::AppliersErrors::ChaptersFileNotCompatible;
+
+    //Precalculate 100*(pow(60,n)) to avoid inconsistency between bench runs
+    let multipliers = [1000, 60 * 1000, 60 * 60 * 1000u32];
+    let parse = |value: &str| value.parse::<u32>().map_err(|_| ChaptersFileNotCompatible);
+
+    //European locales export the decimal part after a comma
+    let (hh_mm_ss, fraction) = time.split_once(['.', ',']).unwrap_or((time, "0"));
+    //The fraction is a decimal part of a second: .5, .50 and .500 are all 500ms
+    let milliseconds = format!("{:0<3.3}", fraction);
+    let hh_mm_ss: Vec<_> = hh_mm_ss.split(':').collect();
+    ensure!(
+        hh_mm_ss.len() <= multipliers.len(),
+        ChaptersFileNotCompatible
+    );
+
+    //Anything past u32::MAX milliseconds (about 49 days) is a broken timestamp, not a long show
+    let time = hh_mm_ss
+        .into_iter()
+        .rev()
+        .zip(multipliers)
+        .map(|(val, multiplier)| {
+            parse(val)?
+                .checked_mul(multiplier)
+                .ok_or(ChaptersFileNotCompatible)
+        })
+        .chain(iter::once(parse(&milliseconds)))
+        .try_fold(0u32, |total, value| {
+            total.checked_add(value?).ok_or(ChaptersFileNotCompatible)
+        });
+    Ok(time?)
+}
+
+/// Formats milliseconds as `hh:mm:ss.mmm`, the inverse of [`convert_time`]
+pub fn format_time(time: u32) -> String {
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        time / (60 * 60 * 1000),
+        time / (60 * 1000) % 60,
+        time / 1000 % 60,
+        time % 1000
+    )
+}
+
+/// Parses a timecode into milliseconds with the rules applied to the CSV markers
+///
+/// ```
+/// use presquile::{format_timecode, parse_timecode};
+///
+/// let milliseconds = parse_timecode("01:02:03.456").unwrap();
+/// assert_eq!(milliseconds, 3_723_456);
+/// assert_eq!(format_timecode(milliseconds), "01:02:03.456");
+/// assert!(parse_timecode("1:02:03:04").is_err());
+/// ```
+pub fn parse_timecode(s: &str) -> Result<u32> {
+    convert_time(s)
+}
+
+/// Formats milliseconds the way chapters are reported, [`parse_timecode`] reads it back
+///
+/// ```
+/// use presquile::{format_timecode, parse_timecode};
+///
+/// assert_eq!(format_timecode(3_723_456), "01:02:03.456");
+/// assert_eq!(parse_timecode(&format_timecode(5_000)).unwrap(), 5_000);
+/// ```
+pub fn format_timecode(ms: u32) -> String {
+    format_time(ms)
+}
+
+fn chapter_title(chapter: &Chapter) -> &str {
+    chapter
+        .frames
+        .iter()
+        .find(|frame| frame.id() == "TIT2")
+        .and_then(|frame| frame.content().text())
+        .unwrap_or_default()
+}
+
+/// Builds one chapter per record, each ending where the next one starts or at `duration`
+pub fn build_chapters(mut records: AuditionCvsRecords, duration: f64) -> Result<Vec<Chapter>> {
+    let mut warnings = Vec::new();
+    name_untitled(&mut records, UNTITLED_PREFIX);
+    let chapters = chain_chapters(records, duration, CHAPTER_ID_PREFIX, false, &mut warnings)?;
+    warnings.iter().for_each(|warning| warn!("{}", warning));
+    Ok(chapters)
+}
+
+const CHAPTER_ID_PREFIX: &str = "chp";
+
+/// Byte offset telling the chapter is located by its times only
+const NO_OFFSET: u32 = u32::MAX;
+
+const UNTITLED_PREFIX: &str = "Chapter";
+
+/// Names the markers left without one `prefix` followed by their 1-based position,
+/// a blank `TIT2` shows as nothing in players
+fn name_untitled(records: &mut AuditionCvsRecords, prefix: &str) {
+    for (idx, record) in records.iter_mut().enumerate() {
+        if record.name.trim().is_empty() {
+            record.name = format!("{} {}", prefix, idx + 1);
+        }
+    }
+}
+
+/// The lines of [`ApplyReport::preview`]
+fn preview(chapters: &[Chapter]) -> Vec<String> {
+    chapters
+        .iter()
+        .map(|chapter| {
+            format!(
+                "{}\t{} --> {}\t{}",
+                chapter.element_id,
+                format_time(chapter.start_time),
+                format_time(chapter.end_time),
+                chapter_title(chapter)
+            )
+        })
+        .collect()
+}
+
+/// `prefix` followed by the 1-based index, zero padded to fit `count` so that ids sort like
+/// the chapters do
+fn chapter_id(prefix: &str, idx: usize, count: usize) -> String {
+    let width = count.to_string().len().max(3);
+    format!("{}{:0width$}", prefix, idx + 1, width = width)
+}
+
+/// The `Id` column of the record when set, the numbered [`chapter_id`] otherwise
+fn element_id(record: &AuditionCvsRecord, prefix: &str, idx: usize, count: usize) -> String {
+    match record.id.as_deref().map(str::trim) {
+        Some(element_id) if !element_id.is_empty() => element_id.to_string(),
+        _ => chapter_id(prefix, idx, count),
+    }
+}
+
+/// Ends each chapter where the next one starts, unless it has a duration and `gapless` is off
+fn chain_chapters(
+    records: AuditionCvsRecords,
+    duration: f64,
+    id_prefix: &str,
+    gapless: bool,
+    warnings: &mut Vec<Warning>,
+) -> Result<Vec<Chapter>> {
+    let count = records.len();
+    let mut end_time = duration as u32;
+    let mut chapters = records
+        .into_iter()
+        .enumerate()
+        .rev()
+        .map(|(id, record)| {
+            let start_time = convert_time(&record.start)?;
+            //Cue markers are exported with a zero duration, chain those to the next marker
+            let duration = record.duration.as_deref().map(convert_time);
+            let chapter_end = match duration.transpose()? {
+                //Clamped to the audio like any other chapter running past its end
+                Some(duration) if duration > 0 && !gapless => start_time.saturating_add(duration),
+                _ => end_time,
+            };
+            let ch = Chapter {
+                element_id: element_id(&record, id_prefix, id, count),
+                start_time,
+                end_time: chapter_end,
+                start_offset: 0,
+                end_offset: 0,
+                frames: chapter_frames(record)?,
+            };
+            end_time = start_time;
+            Ok(ch)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    chapters.reverse();
+
+    //Ids from the CSV may clash with each other or with the numbered ones
+    let mut ids = HashSet::new();
+    for chapter in &chapters {
+        ensure!(
+            ids.insert(&chapter.element_id),
+            AppliersErrors::DuplicateId(chapter.element_id.clone())
+        );
+    }
+
+    //Markers exported from a longer edit may point past the end of the audio
+    let audio_end = duration as u32;
+    chapters.retain(|chapter| {
+        let in_range = chapter.start_time < audio_end;
+        if !in_range {
+            warnings.push(Warning::DroppedPastEnd {
+                title: chapter_title(chapter).to_string(),
+                start: chapter.start_time,
+                audio_end,
+            });
+        }
+        in_range
+    });
+    for chapter in chapters
+        .iter_mut()
+        .filter(|chapter| chapter.end_time > audio_end)
+    {
+        warnings.push(Warning::ClampedToDuration {
+            title: chapter_title(chapter).to_string(),
+            end_time: chapter.end_time,
+            audio_end,
+        });
+        chapter.end_time = audio_end;
+    }
+
+    validate_chapters(&chapters)?;
+    Ok(chapters)
 }
 
-pub fn apply(audition_cvs: PathBuf, mp3_file: PathBuf, parallel: Mode) -> Result<PathBuf> {
-    match parallel {
-        Mode::Sequential => Applier::new(audition_cvs, mp3_file).apply_seq(),
-        Mode::Parallel => Applier::new(audition_cvs, mp3_file).apply_parallel(),
+/// `TIT2` title, plus the `TIT3` description, the `WXXX` link and the `APIC` image when the
+/// record has them
+fn chapter_frames(record: AuditionCvsRecord) -> Result<Vec<Frame>> {
+    let present = |value: &String| !value.trim().is_empty();
+    let picture = match &record.image {
+        Some(image) => Some(chapter_picture(&record.name, image)?),
+        None => None,
+    };
+    let frames = iter::once(Frame::text("TIT2", record.name))
+        .chain(
+            record
+                .description
+                .filter(present)
+                .map(|description| Frame::text("TIT3", description)),
+        )
+        .chain(record.url.filter(present).map(|link| {
+            Frame::from(ExtendedLink {
+                description: String::new(),
+                link,
+            })
+        }))
+        .chain(picture.map(Frame::from))
+        .collect();
+    Ok(frames)
+}
+
+fn chapter_picture(name: &str, image: &Path) -> Result<Picture> {
+    let error = || AppliersErrors::ChapterImage(name.to_string(), image.display().to_string());
+    let extension = image
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase);
+    let mime_type = match extension.as_deref() {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        _ => bail!(error()),
+    };
+    let data = read(image).map_err(|_| error())?;
+    Ok(Picture {
+        mime_type: mime_type.to_string(),
+        picture_type: PictureType::Other,
+        description: String::new(),
+        data,
+    })
+}
+
+fn validate_chapters(chapters: &[Chapter]) -> Result<()> {
+    for pair in chapters.windows(2) {
+        ensure!(
+            pair[0].end_time <= pair[1].start_time,
+            AppliersErrors::OverlappingChapters(
+                chapter_title(&pair[0]).to_owned(),
+                chapter_title(&pair[1]).to_owned()
+            )
+        );
+    }
+    Ok(())
+}
+
+/// Every pass applied to the markers before they are written, shared by apply and verify
+fn process_chapters(
+    mut records: AuditionCvsRecords,
+    duration: f64,
+    options: &ApplyOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<Vec<Chapter>> {
+    let id_prefix = options
+        .chapter_id_prefix
+        .as_deref()
+        .unwrap_or(CHAPTER_ID_PREFIX);
+    let untitled_prefix = options
+        .untitled_prefix
+        .as_deref()
+        .unwrap_or(UNTITLED_PREFIX);
+    if let Some(offset) = options.start_offset_ms {
+        shift_markers(&mut records, offset, duration, warnings)?;
+    }
+    if let Some(title) = &options.intro_title {
+        prepend_intro(&mut records, title)?;
+    }
+    name_untitled(&mut records, untitled_prefix);
+    if let Some(max_len) = options.max_title_len {
+        check_titles(&records, max_len, warnings);
+    }
+    check_span(&records, duration, options, warnings)?;
+    let chapters = chain_chapters(records, duration, id_prefix, options.gapless, warnings)?;
+    enforce_min_duration(chapters, options)
+}
+
+/// Moves every marker by `offset` milliseconds, keeping them between zero and `duration`
+fn shift_markers(
+    records: &mut AuditionCvsRecords,
+    offset: i64,
+    duration: f64,
+    warnings: &mut Vec<Warning>,
+) -> Result<()> {
+    let mut moved = Vec::with_capacity(records.len());
+    for record in records.iter_mut() {
+        let start = convert_time(&record.start)? as i64 + offset;
+        record.start = format_time(start.clamp(0, duration as i64) as u32);
+        moved.push(start);
+    }
+    //Markers moved before zero would all start there, only the last of them is still playing
+    let Some(last) = moved.iter().rposition(|start| *start <= 0) else {
+        return Ok(());
+    };
+    let dropped = moved[..last].iter().take_while(|start| **start < 0).count();
+    for record in records.drain(..dropped) {
+        warnings.push(Warning::DroppedBeforeStart { title: record.name });
+    }
+    Ok(())
+}
+
+/// Only advisory, car head units and the like cut long titles short
+fn check_titles(records: &AuditionCvsRecords, max_len: usize, warnings: &mut Vec<Warning>) {
+    for record in records {
+        let len = record.name.chars().count();
+        if len > max_len {
+            warnings.push(Warning::LongTitle {
+                title: record.name.clone(),
+                len,
+                max_len,
+            });
+        }
+    }
+}
+
+/// Takes out the level 1 markers followed by deeper ones, they group chapters instead of
+/// being one. A part ends where the next level 1 marker starts
+fn split_parts(records: &mut AuditionCvsRecords) -> Result<Vec<Part>> {
+    let level = |record: Option<&AuditionCvsRecord>| record.and_then(|record| record.level);
+    let mut parts = Vec::new();
+    let mut idx = 0;
+    while idx < records.len() {
+        let nested = level(records.get(idx + 1)).is_some_and(|level| level > 1);
+        if level(records.get(idx)).unwrap_or(1) > 1 || !nested {
+            idx += 1;
+            continue;
+        }
+        let part = records.remove(idx);
+        let end = records[idx..]
+            .iter()
+            .find(|record| record.level.unwrap_or(1) <= 1)
+            .map_or(Ok(OPEN_END), |record| convert_time(&record.start))?;
+        parts.push(Part {
+            title: part.name,
+            start_time: convert_time(&part.start)?,
+            end_time: end,
+        });
+    }
+    Ok(parts)
+}
+
+/// Covers the audio before the first marker, left out of every chapter otherwise
+fn prepend_intro(records: &mut AuditionCvsRecords, title: &str) -> Result<()> {
+    let first = match records.first() {
+        Some(first) => convert_time(&first.start)?,
+        None => return Ok(()),
+    };
+    if first > 0 {
+        let intro = AuditionCvsRecord {
+            name: title.to_string(),
+            start: format_time(0),
+            ..Default::default()
+        };
+        records.insert(0, intro);
+    }
+    Ok(())
+}
+
+const MIN_LAST_CHAPTER_RATIO: f64 = 0.01;
+
+/// Markers exported before the audio was edited again end up short of its end, or past it
+fn check_span(
+    records: &AuditionCvsRecords,
+    duration: f64,
+    options: &ApplyOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<()> {
+    let (Some(first), Some(last)) = (records.first(), records.last()) else {
+        return Ok(());
+    };
+    //Players tend to expect the first chapter to cover the opening, see `intro_title`
+    let first_start = convert_time(&first.start)?;
+    if first_start > 0 {
+        let warning = Warning::LateFirstChapter {
+            title: first.name.clone(),
+            start: first_start,
+        };
+        ensure!(
+            !options.strict_span,
+            AppliersErrors::MarkersSpan(warning.to_string())
+        );
+        warnings.push(warning);
+    }
+
+    let start = convert_time(&last.start)? as f64;
+    let ratio = options
+        .min_last_chapter_ratio
+        .unwrap_or(MIN_LAST_CHAPTER_RATIO);
+    if start >= duration {
+        let issue = format!(
+            "last marker \"{}\" starts at {}, after the end of the audio at {}",
+            last.name,
+            format_time(start as u32),
+            format_time(duration as u32)
+        );
+        //Markers past the end already get a warning when they are dropped
+        ensure!(!options.strict_span, AppliersErrors::MarkersSpan(issue));
+    } else if duration - start < duration * ratio {
+        let warning = Warning::ShortLastChapter {
+            title: last.name.clone(),
+            length: (duration - start) as u32,
+            ratio,
+        };
+        ensure!(
+            !options.strict_span,
+            AppliersErrors::MarkersSpan(warning.to_string())
+        );
+        warnings.push(warning);
+    }
+    Ok(())
+}
+
+fn enforce_min_duration(chapters: Vec<Chapter>, options: &ApplyOptions) -> Result<Vec<Chapter>> {
+    let mut kept: Vec<Chapter> = Vec::with_capacity(chapters.len());
+    //A short opening chapter has no predecessor, the next one absorbs it instead
+    let mut pending: Option<Chapter> = None;
+
+    for mut chapter in chapters {
+        if let Some(short) = pending.take() {
+            chapter.start_time = short.start_time;
+        }
+
+        let length = chapter.end_time.saturating_sub(chapter.start_time);
+        if length >= options.min_chapter_duration {
+            kept.push(chapter);
+            continue;
+        }
+        ensure!(
+            options.merge_short_chapters,
+            AppliersErrors::ChapterTooShort(chapter_title(&chapter).to_owned(), length)
+        );
+
+        match kept.last_mut() {
+            Some(previous) => previous.end_time = chapter.end_time,
+            None => pending = Some(chapter),
+        }
+    }
+
+    kept.extend(pending);
+    Ok(kept)
+}
+
+fn sniff_delimiter(content: &[u8]) -> Option<u8> {
+    let line = content
+        .split(|byte| *byte == b'\n')
+        .find(|line| !line.trim_ascii().is_empty() && !line.starts_with(b"#"))?;
+
+    let mut counts = [(b'\t', 0), (b',', 0), (b';', 0)];
+    let mut quoted = false;
+    for byte in line {
+        match byte {
+            b'"' => quoted = !quoted,
+            _ if quoted => {}
+            _ => {
+                if let Some((_, count)) = counts.iter_mut().find(|(delim, _)| delim == byte) {
+                    *count += 1;
+                }
+            }
+        }
+    }
+
+    //Reverse so that ties are won by tab, the Audition default
+    counts
+        .into_iter()
+        .rev()
+        .filter(|(_, count)| *count > 0)
+        .max_by_key(|(_, count)| *count)
+        .map(|(delim, _)| delim)
+}
+
+fn load_cvs(audition_cvs: &Path, options: &ApplyOptions) -> Result<AuditionCvsRecords> {
+    let mut warnings = Vec::new();
+    let records = load_cvs_reporting(audition_cvs, options, &mut warnings)?;
+    warnings.iter().for_each(|warning| warn!("{}", warning));
+    Ok(records)
+}
+
+/// Reads the `[hh:]mm:ss Title` lines of a YouTube description, other lines are ignored
+pub fn load_youtube(path: PathBuf) -> Result<AuditionCvsRecords> {
+    let mut data = read_to_string(path)?
+        .lines()
+        .filter_map(import::parse_line)
+        .map(|record| Ok((convert_time(&record.start)?, record)))
+        .collect::<Result<Vec<_>>>()?;
+    ensure!(!data.is_empty(), AppliersErrors::ChaptersFileNotCompatible);
+
+    data.sort_by_key(|(start, _)| *start);
+    Ok(data.into_iter().map(|(_, record)| record).collect())
+}
+
+const STDIN: &str = "-";
+
+fn load_cvs_reporting(
+    audition_cvs: &Path,
+    options: &ApplyOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<AuditionCvsRecords> {
+    //Like most command line tools, `-` stands for the standard input
+    let mut records = if audition_cvs == Path::new(STDIN) {
+        read_cvs(stdin().lock(), options, warnings)?
+    } else {
+        read_cvs(File::open(audition_cvs)?, options, warnings)?
+    };
+    let base = audition_cvs.parent().unwrap_or(Path::new(""));
+    for image in records
+        .iter_mut()
+        .filter_map(|record| record.image.as_mut())
+    {
+        *image = base.join(&image);
+    }
+    if options.normalize_titles {
+        for record in records.iter_mut() {
+            record.name = normalize_title(&record.name);
+        }
+    }
+    Ok(records)
+}
+
+/// Names pasted from elsewhere often carry stray spaces, non-breaking ones included, or
+/// invisible control characters
+fn normalize_title(title: &str) -> String {
+    title
+        .split(char::is_whitespace)
+        .map(|word| word.chars().filter(|c| !c.is_control()).collect::<String>())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Same parsing and validation as the file based path, for CSV data that never hits the disk
+pub fn load_cvs_from_reader(reader: impl Read) -> Result<AuditionCvsRecords> {
+    read_cvs(reader, &ApplyOptions::default(), &mut Vec::new())
+}
+
+/// Deserializes a single CSV row and parses its start
+fn read_row(record: &StringRecord, headers: &StringRecord) -> Result<(u32, AuditionCvsRecord)> {
+    let record: AuditionCvsRecord = record.deserialize(Some(headers))?;
+    if let Some(duration) = &record.duration {
+        convert_time(duration)?;
+    }
+    Ok((convert_time(&record.start)?, record))
+}
+
+fn raw_line(content: &[u8], position: &Position) -> String {
+    let line = content[position.byte() as usize..]
+        .split(|byte| *byte == b'\n')
+        .next()
+        .unwrap_or_default();
+    String::from_utf8_lossy(line)
+        .trim_end_matches('\r')
+        .to_string()
+}
+
+/// Strips a UTF-8 byte order mark and transcodes UTF-16 files, as written by Windows tools
+fn decode(content: Vec<u8>) -> Result<Vec<u8>> {
+    if let Some(content) = content.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        return Ok(content.to_vec());
+    }
+    let from_bytes = match content.get(..2) {
+        Some([0xFF, 0xFE]) => u16::from_le_bytes,
+        Some([0xFE, 0xFF]) => u16::from_be_bytes,
+        _ => return Ok(content),
+    };
+    ensure!(
+        content.len().is_multiple_of(2),
+        AppliersErrors::ChaptersFileNotCompatible
+    );
+    let units = content[2..]
+        .chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]));
+    match char::decode_utf16(units).collect::<Result<String, _>>() {
+        Ok(text) => Ok(text.into_bytes()),
+        Err(_) => bail!(AppliersErrors::ChaptersFileNotCompatible),
+    }
+}
+
+fn read_cvs(
+    mut reader: impl Read,
+    options: &ApplyOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<AuditionCvsRecords> {
+    let mut content = Vec::new();
+    reader.read_to_end(&mut content)?;
+    let content = decode(content)?;
+    //Parse every start once, both the check and the sort reuse it
+    let mut data = if options.columns.is_none() && import::is_labels(&content) {
+        read_labels(&content, options, warnings)?
+    } else {
+        read_audition(&content, options, warnings)?
+    };
+    if let Some(marker_type) = &options.marker_type {
+        data.retain(|(_, record)| {
+            record
+                .marker_type
+                .as_ref()
+                .is_some_and(|kind| kind.eq_ignore_ascii_case(marker_type))
+        });
+    }
+    ensure!(!data.is_empty(), AppliersErrors::ChaptersFileNotCompatible);
+
+    if options.strict_order {
+        for (row, pair) in data.windows(2).enumerate() {
+            let ((previous_start, previous), (start, record)) = (&pair[0], &pair[1]);
+            ensure!(
+                start >= previous_start,
+                AppliersErrors::UnsortedMarkers(
+                    row + 2,
+                    record.start.clone(),
+                    previous.start.clone()
+                )
+            );
+        }
+    } else {
+        data.sort_by_key(|(start, _)| *start);
+    }
+
+    //Two markers dropped on the same spot would leave a zero length chapter between them,
+    //a part starts along with its first chapter though
+    data.dedup_by(|(start, record), (previous_start, previous)| {
+        let duplicate = start == previous_start && record.level == previous.level;
+        if duplicate {
+            warnings.push(Warning::DroppedZeroLength {
+                title: record.name.clone(),
+                start: *start,
+                previous: previous.name.clone(),
+            });
+        }
+        duplicate
+    });
+    Ok(data.into_iter().map(|(_, record)| record).collect())
+}
+
+fn read_audition(
+    content: &[u8],
+    options: &ApplyOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<Vec<(u32, AuditionCvsRecord)>> {
+    let delimiter = sniff_delimiter(content);
+    ensure!(
+        delimiter.is_some(),
+        AppliersErrors::ChaptersFileNotCompatible
+    );
+    let delimiter = delimiter.unwrap();
+
+    let mut tab_warnings = Vec::new();
+    let data = read_delimited(content, delimiter, options, &mut tab_warnings);
+    //Stray tabs pasted by a spreadsheet can outnumber the commas of the header: before giving
+    //up on a sniffed tab, read the file once more as the other common export
+    match data {
+        Ok(data) if delimiter != b'\t' || !data.is_empty() => {
+            warnings.extend(tab_warnings);
+            return Ok(data);
+        }
+        Err(err) if delimiter != b'\t' => return Err(err),
+        _ => {}
+    }
+    let mut comma_warnings = Vec::new();
+    match read_delimited(content, b',', options, &mut comma_warnings) {
+        Ok(comma_data) if !comma_data.is_empty() => {
+            warnings.extend(comma_warnings);
+            Ok(comma_data)
+        }
+        _ => {
+            warnings.extend(tab_warnings);
+            data
+        }
+    }
+}
+
+fn read_delimited(
+    content: &[u8],
+    delimiter: u8,
+    options: &ApplyOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<Vec<(u32, AuditionCvsRecord)>> {
+    let mut rdr = ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(options.columns.is_none())
+        //Quoted titles may contain the delimiter or a tab
+        .quoting(true)
+        .comment(Some(b'#'))
+        .trim(csv::Trim::All)
+        .from_reader(content);
+
+    let headers = match (&options.columns, rdr.headers()) {
+        (Some(columns), _) => columns.iter().map(|column| column_header(column)).collect(),
+        (None, Ok(headers)) => headers.clone(),
+        (None, Err(_)) => bail!(AppliersErrors::ChaptersFileNotCompatible),
+    };
+    //Custom columns are read under the label of their field, in place of the usual ones
+    let custom = [
+        (options.name_column.as_deref(), "Name", &NAME_LABELS[..]),
+        (options.start_column.as_deref(), "Start", &START_LABELS[..]),
+    ];
+    let renamed: StringRecord = headers
+        .iter()
+        .map(|header| custom_header(header, &custom))
+        .collect();
+    //Without these every row would fail, that's a wrong file rather than a broken line
+    ensure!(
+        [&NAME_LABELS[..], &START_LABELS[..]]
+            .iter()
+            .all(|labels| renamed.iter().any(|header| labels.contains(&header))),
+        AppliersErrors::ChaptersFileNotCompatible
+    );
+    if options.strict_columns {
+        //Columns left out for a custom one are as unused as unknown ones
+        let read = |header| KNOWN_LABELS.contains(&custom_header(header, &custom));
+        warnings.extend(headers.iter().filter(|header| !read(header)).map(|header| {
+            Warning::UnexpectedColumn {
+                column: header.to_string(),
+            }
+        }));
+    }
+    let mut record = StringRecord::new();
+    let mut data = Vec::new();
+    loop {
+        let position = rdr.position().clone();
+        let row = match rdr.read_record(&mut record) {
+            Ok(false) => break,
+            //Lines holding nothing but delimiters are as blank as empty ones
+            Ok(true) if record.iter().all(str::is_empty) => continue,
+            Ok(true) => read_row(&record, &renamed),
+            Err(err) => Err(err.into()),
+        };
+        match row {
+            Ok(row) => data.push(row),
+            Err(_) => skip_row(
+                position.line(),
+                raw_line(content, &position),
+                options,
+                warnings,
+            )?,
+        }
+    }
+    Ok(data)
+}
+
+/// Header serde reads in place of `header`: the label of the field a custom column was given
+/// for, nothing for the columns that field is otherwise read from
+fn custom_header<'a>(header: &'a str, custom: &[(Option<&str>, &'a str, &[&str])]) -> &'a str {
+    for (column, label, labels) in custom {
+        match column {
+            Some(column) if *column == header => return label,
+            Some(_) if labels.contains(&header) => return "",
+            _ => (),
+        }
+    }
+    header
+}
+
+/// Maps a user given column name such as `start` to the header serde expects
+fn column_header(column: &str) -> String {
+    let mut chars = column.trim().chars();
+    chars
+        .next()
+        .map(|first| {
+            first
+                .to_uppercase()
+                .chain(chars.flat_map(char::to_lowercase))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn read_labels(
+    content: &[u8],
+    options: &ApplyOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<Vec<(u32, AuditionCvsRecord)>> {
+    let mut data = Vec::new();
+    for (line, raw) in String::from_utf8_lossy(content).lines().enumerate() {
+        //Spectral selections follow their label on a line starting with a backslash
+        if raw.trim().is_empty() || raw.starts_with('\\') {
+            continue;
+        }
+        match import::parse_label(raw) {
+            Ok(row) => data.push(row),
+            Err(_) => skip_row(line as u64 + 1, raw.to_string(), options, warnings)?,
+        }
+    }
+    Ok(data)
+}
+
+/// A row that failed to parse is fatal unless the options are lenient
+fn skip_row(
+    line: u64,
+    raw: String,
+    options: &ApplyOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<()> {
+    if !options.lenient {
+        bail!(AppliersErrors::InvalidRow(line, raw));
+    }
+    warnings.push(Warning::SkippedBadRow { line, row: raw });
+    Ok(())
+}
+
+const SUFFIX: &str = "_enriched";
+
+/// Replaces the extension of the audio file to name the sidecar, as mp3chaps expects
+const SIDECAR_EXTENSION: &str = "chapters.txt";
+
+fn enriched_path(mp3_file: &Path, suffix: &str) -> Result<PathBuf> {
+    let file_name = mp3_file.file_stem().and_then(|file| file.to_str());
+    ensure!(file_name.is_some(), AppliersErrors::CopyFile);
+
+    //Re-running on an enriched file updates it instead of stacking suffixes
+    if file_name.unwrap().ends_with(suffix) {
+        return Ok(mp3_file.to_path_buf());
+    }
+
+    let extension = mp3_file
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("mp3");
+    Ok(mp3_file.with_file_name(format!("{}{}.{}", file_name.unwrap(), suffix, extension)))
+}
+
+fn backup_path(file: &Path) -> PathBuf {
+    let mut backup = file.as_os_str().to_owned();
+    backup.push(".bak");
+    backup.into()
+}
+
+/// Staged files created by this process so far, keeps their names apart
+static STAGED: AtomicUsize = AtomicUsize::new(0);
+
+/// Creates an empty hidden sibling of `destination` where the output is assembled, the same
+/// filesystem guarantees the final rename is atomic. Every call gets its own file, so applies
+/// writing the same output never share one
+fn staging_path(destination: &Path) -> Result<PathBuf> {
+    let file_name = destination.file_name().and_then(|file| file.to_str());
+    ensure!(file_name.is_some(), AppliersErrors::CopyFile);
+    if let Some(parent) = destination.parent() {
+        create_dir_all(parent)?;
+    }
+    loop {
+        let staged = destination.with_file_name(format!(
+            ".presquile-{}-{}-{}",
+            process::id(),
+            STAGED.fetch_add(1, Ordering::Relaxed),
+            file_name.unwrap()
+        ));
+        //Left behind by a crashed run with a recycled pid
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&staged)
+        {
+            Ok(_) => return Ok(staged),
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+/// Lets `write` finish on the staged copy, then renames it over `destination`.
+/// On failure the staged copy is removed and `destination` is left untouched
+fn publish(
+    staged: &Path,
+    destination: &Path,
+    write: impl FnOnce(&Path) -> Result<()>,
+) -> Result<PathBuf> {
+    if let Err(err) = write(staged).and_then(|_| Ok(rename(staged, destination)?)) {
+        let _ = remove_file(staged);
+        return Err(err);
+    }
+    Ok(destination.to_path_buf())
+}
+
+fn copy_file(source: &Path, destination: &Path, progress: Option<CopyProgress>) -> Result<PathBuf> {
+    if let Some(parent) = destination.parent() {
+        create_dir_all(parent)?;
+    }
+    match progress {
+        None => {
+            copy(source, destination)?;
+        }
+        Some(progress) => copy_chunked(source, destination, progress)?,
+    }
+
+    Ok(destination.to_path_buf())
+}
+
+const COPY_CHUNK: usize = 1024 * 1024;
+
+const COPY_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Runs `run` up to `retries` more times while it fails with an IO error, waiting `delay` before
+/// the first retry and twice as long before each of the next ones
+fn retry<T>(retries: u32, mut delay: Duration, mut run: impl FnMut() -> Result<T>) -> Result<T> {
+    let mut attempt = 0;
+    loop {
+        match run() {
+            //A missing file stays missing, nothing transient about it
+            Err(err)
+                if attempt < retries
+                    && err
+                        .downcast_ref::<io::Error>()
+                        .is_some_and(|err| err.kind() != ErrorKind::NotFound) =>
+            {
+                attempt += 1;
+                warn!("Attempt {attempt} failed: {err}, retrying in {delay:?}");
+                thread::sleep(delay);
+                delay *= 2;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// `fs::copy` one chunk at a time, reporting the bytes copied after each one
+fn copy_chunked(source: &Path, destination: &Path, progress: CopyProgress) -> Result<()> {
+    let mut reader = File::open(source)?;
+    let metadata = reader.metadata()?;
+    let mut writer = File::create(destination)?;
+    let mut buffer = vec![0; COPY_CHUNK];
+    let mut copied = 0;
+    progress(copied, metadata.len());
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..read])?;
+        copied += read as u64;
+        progress(copied, metadata.len());
+    }
+    set_permissions(destination, metadata.permissions())?;
+    Ok(())
+}
+
+/// Removes every chapter and table of contents from an mp3, leaving the other frames untouched.
+/// Returns `None` without writing anything when there is nothing to remove
+pub fn strip(mp3_file: PathBuf) -> Result<Option<PathBuf>> {
+    strip_with_options(mp3_file, &ApplyOptions::default())
+}
+
+pub fn strip_with_output(mp3_file: PathBuf, output: PathBuf) -> Result<Option<PathBuf>> {
+    strip_with_options(mp3_file, &ApplyOptions::default().with_output(output))
+}
+
+/// [`strip`] writing where [`apply_with_options`] would: `in_place`, `output`, `output_dir`,
+/// `suffix` and `keep_existing` are honored, the sidecar is not
+pub fn strip_with_options(mp3_file: PathBuf, options: &ApplyOptions) -> Result<Option<PathBuf>> {
+    let options = ApplyOptions {
+        sidecar: false,
+        ..options.clone()
+    };
+    let output = output_path(&mp3_file, &options)?;
+    let mut tag = match no_tag_ok(Tag::read_from_path(&mp3_file))? {
+        Some(tag) => tag,
+        None => return Ok(None),
+    };
+    if tag.chapters().next().is_none() && tag.tables_of_contents().next().is_none() {
+        return Ok(None);
+    }
+    ensure!(
+        !options.keep_existing || output == mp3_file || !output.exists(),
+        AppliersErrors::OutputExists(output.display().to_string())
+    );
+
+    tag.remove_all_chapters();
+    tag.remove_all_tables_of_contents();
+    let staged = staging_path(&output)?;
+    if let Err(err) = copy_file(&mp3_file, &staged, None) {
+        let _ = remove_file(&staged);
+        return Err(err);
+    }
+    let new_mp3_file = publish(&staged, &output, |file| {
+        Ok(tag.write_to_path(file, tag.version())?)
+    })?;
+    Ok(Some(new_mp3_file))
+}
+
+/// Runs one step of an apply, timing it for the logs
+fn timed<T>(step: &str, run: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = run();
+    info!("{} took {:?}", step, start.elapsed());
+    result
+}
+
+/// Runs `run` on a detached thread, its result is collected with [`await_step`]
+fn spawn_step<T: Send + 'static>(
+    step: &'static str,
+    run: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Receiver<Result<T>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        //Nobody listens anymore once the step timed out
+        let _ = sender.send(timed(step, run));
+    });
+    receiver
+}
+
+fn await_step<T>(step: &str, receiver: Receiver<Result<T>>, deadline: Instant) -> Result<T> {
+    match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => bail!(AppliersErrors::Timeout(step.to_string())),
+        //The sender is only dropped without sending when the step panicked
+        Err(RecvTimeoutError::Disconnected) => bail!(AppliersErrors::ThreadInterrupted),
+    }
+}
+
+#[derive(new)]
+struct Applier<'a> {
+    audition_cvs: PathBuf,
+    mp3_file: PathBuf,
+    output: PathBuf,
+    options: ApplyOptions,
+    progress: Option<CopyProgress<'a>>,
+}
+
+impl Applier<'_> {
+    fn apply_seq(&self) -> Result<ApplyReport> {
+        let mut warnings = Vec::new();
+        let cvs = timed("load_cvs", || {
+            load_cvs_reporting(&self.audition_cvs, &self.options, &mut warnings)
+        })?;
+        let (format, container, duration) = timed("verify_mp3_file", || self.verify_mp3_file())?;
+        let (chapters, parts) = self.build_chapters(cvs, duration, &mut warnings)?;
+        let output = if self.options.dry_run {
+            self.output.clone()
+        } else if self.options.sidecar {
+            timed("write", || self.write_sidecar(&chapters))?
+        } else {
+            let staged = staging_path(&self.output)?;
+            timed("copy_file", || self.prepare_output(&staged))?;
+            timed("write", || self.publish(format, &chapters, &parts, &staged))?
+        };
+        let mut report = ApplyReport::new(output, container, &chapters, warnings);
+        if self.options.dry_run {
+            report.preview = preview(&chapters);
+        }
+        Ok(report)
+    }
+
+    fn apply_parallel(&self) -> Result<ApplyReport> {
+        use crate::AppliersErrors::ThreadInterrupted;
+
+        //Without the copy there is nothing worth overlapping
+        if self.options.dry_run || self.options.sidecar {
+            return self.apply_seq();
+        }
+        if let Some(timeout) = self.options.step_timeout {
+            return self.apply_detached(timeout);
+        }
+
+        let staged = staging_path(&self.output)?;
+        let prepared = thread::scope(|s| {
+            let cvs = s.spawn(|| {
+                let mut warnings = Vec::new();
+                timed("load_cvs", || {
+                    load_cvs_reporting(&self.audition_cvs, &self.options, &mut warnings)
+                })
+                .map(|cvs| (cvs, warnings))
+            });
+            let format = s.spawn(|| timed("verify_mp3_file", || self.verify_mp3_file()));
+            let new_mp3_file = s.spawn(|| timed("copy_file", || self.prepare_output(&staged)));
+
+            //Join the copy first so a failed validation never leaves it behind
+            let new_mp3_file = new_mp3_file.join().map_err(|_| ThreadInterrupted)??;
+            let (cvs, mut warnings) = cvs.join().map_err(|_| ThreadInterrupted)??;
+            let (format, container, duration) = format.join().map_err(|_| ThreadInterrupted)??;
+
+            let (chapters, parts) = self.build_chapters(cvs, duration, &mut warnings)?;
+            anyhow::Ok((format, container, chapters, parts, warnings, new_mp3_file))
+        });
+
+        match prepared {
+            Ok((format, container, chapters, parts, warnings, new_mp3_file)) => {
+                let output = timed("write", || {
+                    self.publish(format, &chapters, &parts, &new_mp3_file)
+                })?;
+                Ok(ApplyReport::new(output, container, &chapters, warnings))
+            }
+            Err(err) => {
+                let _ = remove_file(&staged);
+                Err(err)
+            }
+        }
+    }
+
+    /// [`Self::apply_parallel`] on threads outside of any scope, a step still running after
+    /// `timeout` is left behind instead of being waited for
+    fn apply_detached(&self, timeout: Duration) -> Result<ApplyReport> {
+        let deadline = Instant::now() + timeout;
+        let staged = staging_path(&self.output)?;
+        let applier = Arc::new(Applier::new(
+            self.audition_cvs.clone(),
+            self.mp3_file.clone(),
+            self.output.clone(),
+            self.options.clone(),
+            None,
+        ));
+        let cvs = spawn_step("load_cvs", {
+            let applier = applier.clone();
+            move || {
+                let mut warnings = Vec::new();
+                load_cvs_reporting(&applier.audition_cvs, &applier.options, &mut warnings)
+                    .map(|cvs| (cvs, warnings))
+            }
+        });
+        let format = spawn_step("verify_mp3_file", {
+            let applier = applier.clone();
+            move || applier.verify_mp3_file()
+        });
+        let new_mp3_file = spawn_step("copy_file", {
+            let staged = staged.clone();
+            move || applier.prepare_output(&staged)
+        });
+
+        let prepared = (|| {
+            let new_mp3_file = await_step("copy_file", new_mp3_file, deadline)?;
+            let (cvs, mut warnings) = await_step("load_cvs", cvs, deadline)?;
+            let (format, container, duration) = await_step("verify_mp3_file", format, deadline)?;
+            let (chapters, parts) = self.build_chapters(cvs, duration, &mut warnings)?;
+            anyhow::Ok((format, container, chapters, parts, warnings, new_mp3_file))
+        })();
+
+        match prepared {
+            Ok((format, container, chapters, parts, warnings, new_mp3_file)) => {
+                let output = timed("write", || {
+                    self.publish(format, &chapters, &parts, &new_mp3_file)
+                })?;
+                Ok(ApplyReport::new(output, container, &chapters, warnings))
+            }
+            Err(err) => {
+                let _ = remove_file(&staged);
+                Err(err)
+            }
+        }
+    }
+
+    /// Copies the source to `staged`, ready to receive the chapters, removing it on failure
+    fn prepare_output(&self, staged: &Path) -> Result<PathBuf> {
+        let retries = self.options.copy_retries;
+        let delay = self.options.copy_retry_delay.unwrap_or(COPY_RETRY_DELAY);
+        let copied = (|| {
+            if self.options.in_place && self.options.backup {
+                let backup = backup_path(&self.mp3_file);
+                retry(retries, delay, || copy_file(&self.mp3_file, &backup, None))?;
+            }
+            retry(retries, delay, || {
+                copy_file(&self.mp3_file, staged, self.progress)
+            })
+        })();
+        if copied.is_err() {
+            let _ = remove_file(staged);
+        }
+        copied
+    }
+
+    fn publish(
+        &self,
+        format: AudioFormat,
+        chapters: &[Chapter],
+        parts: &[Part],
+        staged: &Path,
+    ) -> Result<PathBuf> {
+        //Read before the rename, which replaces the source in place mode
+        let modified = match self.options.preserve_mtime {
+            true => Some(FileTime::from_last_modification_time(&metadata(
+                &self.mp3_file,
+            )?)),
+            false => None,
+        };
+        let toc_title = self.toc_title();
+        publish(staged, &self.output, |file| {
+            format
+                .writer(&self.options, parts)
+                .write(chapters, &toc_title, file)?;
+            match modified {
+                Some(modified) => Ok(set_file_mtime(file, modified)?),
+                None => Ok(()),
+            }
+        })
+    }
+
+    /// The `toc_title` option, or else the album of the source, or else `"Chapters"`
+    fn toc_title(&self) -> String {
+        let album = || {
+            let tag = Tag::read_from_path(&self.mp3_file).ok()?;
+            tag.album().map(str::to_string)
+        };
+        match &self.options.toc_title {
+            Some(title) => title.clone(),
+            None => album().unwrap_or_else(|| "Chapters".to_string()),
+        }
+    }
+
+    fn write_sidecar(&self, chapters: &[Chapter]) -> Result<PathBuf> {
+        write(&self.output, export::mp3chaps(chapters))?;
+        Ok(self.output.clone())
+    }
+
+    fn build_chapters(
+        &self,
+        mut cvs: AuditionCvsRecords,
+        duration: f64,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<(Vec<Chapter>, Vec<Part>)> {
+        let markers = cvs.len();
+        let parts = split_parts(&mut cvs)?;
+        let mut chapters = process_chapters(cvs, duration, &self.options, warnings)?;
+        if self.options.byte_offsets {
+            self.fill_byte_offsets(&mut chapters);
+        }
+        debug!(
+            "{} markers make {} chapters over {}ms",
+            markers,
+            chapters.len(),
+            duration
+        );
+        Ok((chapters, parts))
+    }
+
+    fn verify_mp3_file(&self) -> Result<(AudioFormat, String, f64)> {
+        let (format, container, duration) = self.audio_format()?;
+        //Most likely a file enriched by a previous run, the sidecar leaves its tag alone though
+        let replaced = self.options.keep_chapters && !self.options.merge && !self.options.sidecar;
+        if matches!(format, AudioFormat::Mp3) && replaced {
+            let chaptered = Tag::read_from_path(&self.mp3_file).is_ok_and(|tag| {
+                tag.chapters().next().is_some() || tag.tables_of_contents().next().is_some()
+            });
+            ensure!(
+                !chaptered,
+                AppliersErrors::HasChapters(self.mp3_file.display().to_string())
+            );
+        }
+        Ok((format, container, duration))
+    }
+
+    /// Format, container reported by the probe and duration in milliseconds of the source
+    fn audio_format(&self) -> Result<(AudioFormat, String, f64)> {
+        let milliseconds = |duration: Option<f64>| match duration {
+            Some(duration) => Ok(duration * 1000f64),
+            None => Err(AppliersErrors::DurationUnknown(
+                self.mp3_file.display().to_string(),
+            )),
+        };
+        match MediaFileMetadata::new(&self.mp3_file) {
+            Ok(metadata) => {
+                let format = match AudioFormat::from_container(&metadata.container_format) {
+                    Some(format) => format,
+                    None => bail!(AppliersErrors::AudioFileNotCompatible(
+                        self.mp3_file.display().to_string(),
+                        metadata.container_format
+                    )),
+                };
+                let duration = match format {
+                    //VBR files missing their Xing header may have no duration in the metadata
+                    AudioFormat::Mp3 => metadata._duration.or_else(|| self.frames_duration()),
+                    _ => metadata._duration,
+                };
+                Ok((format, metadata.container_format, milliseconds(duration)?))
+            }
+            //Without the metadata crate an mp3 can still be measured from its frames
+            Err(_) => match self.frames_duration() {
+                Some(duration) => Ok((AudioFormat::Mp3, "MP3".to_string(), duration * 1000f64)),
+                None => bail!(AppliersErrors::AudioFileNotCompatible(
+                    self.mp3_file.display().to_string(),
+                    "unknown".to_string()
+                )),
+            },
+        }
+    }
+
+    /// Offsets are counted from the first audio frame, the mp3 writer adds the size of its tag
+    fn fill_byte_offsets(&self, chapters: &mut [Chapter]) {
+        let bitrate = File::open(&self.mp3_file)
+            .and_then(|mut file| probe::mp3_constant_bitrate(&mut file))
+            .ok()
+            .flatten();
+        let offset = |time: u32| match bitrate {
+            Some(bitrate) => (time as u64 * bitrate as u64 / 8000) as u32,
+            None => NO_OFFSET,
+        };
+        for chapter in chapters {
+            chapter.start_offset = offset(chapter.start_time);
+            chapter.end_offset = offset(chapter.end_time);
+        }
+    }
+
+    fn frames_duration(&self) -> Option<f64> {
+        File::open(&self.mp3_file)
+            .and_then(|mut file| probe::mp3_duration(&mut file))
+            .ok()
+            .flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use filetime::{set_file_mtime, FileTime};
+    use id3::{Frame, Tag, TagLike, Version};
+    use metadata::MediaFileMetadata;
+
+    use std::{
+        env::temp_dir,
+        fs::{
+            copy, create_dir_all, hard_link, metadata, read, read_dir, read_to_string,
+            remove_dir_all, remove_file, write, File,
+        },
+        io::{self, Cursor, ErrorKind},
+        path::{Path, PathBuf},
+        sync::Mutex,
+        thread,
+        time::Duration,
+    };
+
+    use crate::{
+        apply, apply_reported, apply_variants, apply_with_options, apply_with_output,
+        apply_with_progress, backup_path, batch, batch_glob, batch_parallel, batch_with_options,
+        batch_with_progress, build_chapters, chapter_id, chapter_title, convert_time, copy_file,
+        enforce_min_duration, enriched_path, expand_patterns, export_ffmetadata,
+        export_podcast_json, export_webvtt, extract, format_time, load_cvs, load_cvs_from_reader,
+        load_cvs_reporting, load_youtube, parse_chapters, probe, process_chapters, publish,
+        read_cvs, retry, staging_path, strip, strip_with_options, verify, writer, AppliersErrors,
+        ApplyOptions, AudioFormat, Chapter, Config, Mode, Warning,
+    };
+
+    macro_rules! test_file {
+        ($file_name:expr) => {
+            concat!(env!("CARGO_MANIFEST_DIR"), "/resources/test/", $file_name)
+        };
+    }
+
+    #[test]
+    fn test_not_audio_parallel() {
+        assert!(apply(
+            test_file!("valid_chaps.cvs").into(),
+            test_file!("file.txt").into(),
+            Mode::Parallel,
+        )
+        .is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::AudioFileNotCompatible(..)) => true,
+            _ => false,
+        }))
+    }
+
+    #[test]
+    fn test_ogg_parallel() {
+        let new_ogg_file = apply_with_output(
+            test_file!("valid_chaps.cvs").into(),
+            test_file!("audio.ogg").into(),
+            temp_dir().join("presquile_ogg_parallel.ogg"),
+            Mode::Parallel,
+        );
+        assert!(new_ogg_file.is_ok());
+
+        let comments = writer::ogg_comments(&read(new_ogg_file.unwrap()).unwrap()).unwrap();
+        assert!(comments.contains(&"CHAPTER001NAME=Intro".to_string()));
+        assert!(comments.contains(&"CHAPTER002NAME=Advertising".to_string()));
+    }
+
+    #[test]
+    fn test_not_cvs_parallel() {
+        assert!(apply(
+            test_file!("file.txt").into(),
+            test_file!("audio.mp3").into(),
+            Mode::Parallel,
+        )
+        .is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::ChaptersFileNotCompatible) => true,
+            _ => false,
+        }))
+    }
+
+    #[test]
+    fn test_invalid_cvs_parallel() {
+        assert!(apply(
+            test_file!("invalid_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            Mode::Parallel,
+        )
+        .is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::ChaptersFileNotCompatible) => true,
+            _ => false,
+        }))
+    }
+
+    #[test]
+    fn test_best_case_parallel() {
+        let source_dir = temp_dir().join("presquile_best_case_parallel");
+        let _ = remove_dir_all(&source_dir);
+        create_dir_all(&source_dir).unwrap();
+        let source = source_dir.join("audio.mp3");
+        copy(test_file!("audio.mp3"), &source).unwrap();
+
+        let new_mp3_file = apply(test_file!("valid_chaps.cvs").into(), source, Mode::Parallel);
+        assert!(new_mp3_file.is_ok());
+
+        let tag = Tag::read_from_path(new_mp3_file.unwrap());
+        assert!(tag.is_ok());
+        let tag = tag.unwrap();
+
+        let chapters: Vec<_> = tag.chapters().collect();
+        assert!(!chapters.is_empty());
+        println!("{:?}", chapters);
+
+        let ctocs: Vec<_> = tag.tables_of_contents().collect();
+        assert_eq!(ctocs.len(), 1);
+        println!("{:?}", ctocs);
+
+        chapters
+            .iter()
+            .zip(ctocs.last().unwrap().elements.iter())
+            .for_each(|(chap, chap_id)| assert_eq!(chap.element_id, *chap_id));
+        assert_eq!(ctocs.last().unwrap().elements, ["chp001", "chp002"]);
+    }
+
+    #[test]
+    fn test_not_audio_seq() {
+        assert!(apply(
+            test_file!("valid_chaps.cvs").into(),
+            test_file!("file.txt").into(),
+            Mode::Sequential,
+        )
+        .is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::AudioFileNotCompatible(..)) => true,
+            _ => false,
+        }))
+    }
+
+    #[test]
+    fn test_copy_retries() {
+        let flaky = |failures: u32| {
+            let mut attempts = 0;
+            move || {
+                attempts += 1;
+                match attempts <= failures {
+                    true => Err(io::Error::new(ErrorKind::TimedOut, "stale handle").into()),
+                    false => Ok(attempts),
+                }
+            }
+        };
+        let delay = Duration::from_millis(1);
+        assert_eq!(retry(2, delay, flaky(2)).unwrap(), 3);
+        assert!(retry(0, delay, flaky(1)).is_err());
+        assert!(retry(1, delay, flaky(2)).is_err());
+
+        //Neither a logical error nor a missing file is worth another attempt
+        let mut attempts = 0;
+        let logical = retry(3, delay, || -> anyhow::Result<()> {
+            attempts += 1;
+            anyhow::bail!(AppliersErrors::CopyFile)
+        });
+        assert!(logical.is_err() && attempts == 1);
+        let mut attempts = 0;
+        let missing = retry(3, delay, || {
+            attempts += 1;
+            copy_file(
+                Path::new(test_file!("missing.mp3")),
+                &temp_dir().join("presquile_retry.mp3"),
+                None,
+            )
+        });
+        assert!(missing.is_err() && attempts == 1);
+
+        let options = ApplyOptions::default()
+            .with_output(temp_dir().join("presquile_retries.mp3"))
+            .with_copy_retries(2)
+            .with_copy_retry_delay(delay);
+        assert!(apply_with_options(
+            test_file!("valid_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            Mode::Sequential,
+            options,
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_probe_container_names() {
+        let formats = [
+            ("MP3", Some(AudioFormat::Mp3)),
+            ("Raw FLAC", Some(AudioFormat::Flac)),
+            ("Waveform Audio (WAV)", Some(AudioFormat::Wav)),
+            ("MPEG-4 Part 14 (M4B)", Some(AudioFormat::Mp4)),
+            ("Ogg", Some(AudioFormat::Ogg)),
+            ("QuickTime File Format", None),
+            ("Matroska (MKV)", None),
+        ];
+        for (container, format) in formats {
+            assert_eq!(
+                AudioFormat::from_container(container),
+                format,
+                "{container}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_container_format() {
+        let report = |source: &str, output: &str| {
+            apply_reported(
+                test_file!("valid_chaps.cvs").into(),
+                Path::new(test_file!("")).join(source),
+                Mode::Sequential,
+                ApplyOptions::default().with_output(temp_dir().join(output)),
+            )
+        };
+        let mp3 = report("audio.mp3", "presquile_container.mp3").unwrap();
+        assert_eq!(mp3.container_format, "MP3");
+        let ogg = report("audio.ogg", "presquile_container.ogg").unwrap();
+        assert_eq!(ogg.container_format, "Ogg");
+
+        //Probed fine but turned down by the writer, which still names what the probe saw
+        let damaged = temp_dir().join("presquile_container_damaged.ogg");
+        let mut content = read(test_file!("audio.ogg")).unwrap();
+        content[0x31] ^= 0xff;
+        write(&damaged, content).unwrap();
+        let err = apply_reported(
+            test_file!("valid_chaps.cvs").into(),
+            damaged,
+            Mode::Sequential,
+            ApplyOptions::default().with_output(temp_dir().join("presquile_container_out.ogg")),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(AppliersErrors::AudioFileNotCompatible(_, format)) if format == "Ogg"
+        ));
+        assert!(err.to_string().ends_with("probed as Ogg"));
+    }
+
+    #[test]
+    fn test_ogg_seq() {
+        let source_dir = temp_dir().join("presquile_ogg_seq");
+        let _ = remove_dir_all(&source_dir);
+        create_dir_all(&source_dir).unwrap();
+        let source = source_dir.join("audio.ogg");
+        copy(test_file!("audio.ogg"), &source).unwrap();
+
+        let new_ogg_file = apply(
+            test_file!("valid_chaps.cvs").into(),
+            source,
+            Mode::Sequential,
+        );
+        assert!(new_ogg_file.is_ok());
+
+        let new_ogg_file = new_ogg_file.unwrap();
+        let comments = writer::ogg_comments(&read(&new_ogg_file).unwrap()).unwrap();
+        let chapters: Vec<_> = comments
+            .iter()
+            .filter(|comment| comment.starts_with("CHAPTER"))
+            .collect();
+        assert_eq!(
+            chapters,
+            [
+                "CHAPTER001=00:00:00.000",
+                "CHAPTER001NAME=Intro",
+                "CHAPTER002=00:01:00.636",
+                "CHAPTER002NAME=Advertising",
+            ]
+        );
+        assert!(comments.contains(&"encoder=Lavc58.115.102 libvorbis".to_string()));
+
+        //Applying again replaces the chapters instead of adding to them
+        let again = apply_with_output(
+            test_file!("valid_chaps.cvs").into(),
+            new_ogg_file,
+            temp_dir().join("presquile_ogg_again.ogg"),
+            Mode::Sequential,
+        )
+        .unwrap();
+        let comments = writer::ogg_comments(&read(again).unwrap()).unwrap();
+        assert_eq!(comments.len(), 5);
+    }
+
+    #[test]
+    fn test_not_cvs_seq() {
+        assert!(apply(
+            test_file!("file.txt").into(),
+            test_file!("audio.mp3").into(),
+            Mode::Sequential,
+        )
+        .is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::ChaptersFileNotCompatible) => true,
+            _ => false,
+        }))
+    }
+
+    #[test]
+    fn test_invalid_cvs_seq() {
+        assert!(apply(
+            test_file!("invalid_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            Mode::Sequential,
+        )
+        .is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::ChaptersFileNotCompatible) => true,
+            _ => false,
+        }))
+    }
+
+    #[test]
+    fn test_best_case_seq() {
+        let source_dir = temp_dir().join("presquile_best_case_seq");
+        let _ = remove_dir_all(&source_dir);
+        create_dir_all(&source_dir).unwrap();
+        let source = source_dir.join("audio.mp3");
+        copy(test_file!("audio.mp3"), &source).unwrap();
+
+        let new_mp3_file = apply(
+            test_file!("valid_chaps.cvs").into(),
+            source,
+            Mode::Sequential,
+        );
+        assert!(new_mp3_file.is_ok());
+
+        let tag = Tag::read_from_path(new_mp3_file.unwrap());
+        assert!(tag.is_ok());
+        let tag = tag.unwrap();
+
+        let chapters: Vec<_> = tag.chapters().collect();
+        assert!(!chapters.is_empty());
+        println!("{:?}", chapters);
+
+        let ctocs: Vec<_> = tag.tables_of_contents().collect();
+        assert_eq!(ctocs.len(), 1);
+        println!("{:?}", ctocs);
+
+        chapters
+            .iter()
+            .zip(ctocs.last().unwrap().elements.iter())
+            .for_each(|(chap, chap_id)| assert_eq!(chap.element_id, *chap_id));
+    }
+
+    #[test]
+    fn test_tab_delimiter() {
+        let records = load_cvs(
+            test_file!("valid_chaps.cvs").as_ref(),
+            &ApplyOptions::default(),
+        );
+        assert!(records.is_ok_and(|records| records.len() == 2));
+    }
+
+    #[test]
+    fn test_comma_delimiter() {
+        let expected = load_cvs(
+            test_file!("valid_chaps.cvs").as_ref(),
+            &ApplyOptions::default(),
+        )
+        .unwrap();
+        let records = load_cvs(
+            test_file!("valid_chaps_comma.cvs").as_ref(),
+            &ApplyOptions::default(),
+        );
+        assert!(records.is_ok_and(|records| records == expected));
+    }
+
+    #[test]
+    fn test_comma_fallback() {
+        let expected = load_cvs(
+            test_file!("valid_chaps.cvs").as_ref(),
+            &ApplyOptions::default(),
+        )
+        .unwrap();
+        //The header holds more tabs than commas, so it's first read as tab separated
+        let records = load_cvs(
+            test_file!("tab_padded_comma_chaps.cvs").as_ref(),
+            &ApplyOptions::default(),
+        );
+        assert!(records.is_ok_and(|records| records == expected));
+
+        let records = read_cvs(
+            &b"Name;Start\t\t\nIntro;0:00.000\n"[..],
+            &ApplyOptions::default(),
+            &mut Vec::new(),
+        );
+        assert!(records.is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::ChaptersFileNotCompatible) => true,
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn test_semicolon_delimiter() {
+        let expected = load_cvs(
+            test_file!("valid_chaps.cvs").as_ref(),
+            &ApplyOptions::default(),
+        )
+        .unwrap();
+        let records = load_cvs(
+            test_file!("valid_chaps_semicolon.cvs").as_ref(),
+            &ApplyOptions::default(),
+        );
+        assert!(records.is_ok_and(|records| records == expected));
+    }
+
+    #[test]
+    fn test_localized_headers() {
+        let expected = load_cvs(
+            test_file!("valid_chaps.cvs").as_ref(),
+            &ApplyOptions::default(),
+        )
+        .unwrap();
+        for file in [
+            test_file!("italian_chaps.cvs"),
+            test_file!("german_chaps.cvs"),
+        ] {
+            let records = load_cvs(file.as_ref(), &ApplyOptions::default());
+            assert!(records.is_ok_and(|records| records == expected));
+        }
+    }
+
+    #[test]
+    fn test_quoted_titles() {
+        let records = load_cvs(
+            test_file!("quoted_chaps.cvs").as_ref(),
+            &ApplyOptions::default(),
+        )
+        .unwrap();
+        let names: Vec<_> = records.iter().map(|record| record.name.as_str()).collect();
+        assert_eq!(names, ["Intro\tand welcome", "Part one; the beginning"]);
+        assert_eq!(records[1].start, "0:30.000");
+    }
+
+    #[test]
+    fn test_explicit_duration() {
+        let chapters = parse_chapters(test_file!("duration_chaps.cvs").into(), 120_000f64).unwrap();
+
+        assert_eq!(chapters.len(), 2);
+        assert_eq!((chapters[0].start_time, chapters[0].end_time), (0, 30_000));
+        assert_eq!(
+            (chapters[1].start_time, chapters[1].end_time),
+            (60_636, 81_136)
+        );
+    }
+
+    #[test]
+    fn test_intro_chapter() {
+        let records = || {
+            load_cvs(
+                test_file!("late_start_chaps.cvs").as_ref(),
+                &ApplyOptions::default(),
+            )
+            .unwrap()
+        };
+        let chapters = process_chapters(
+            records(),
+            120_000f64,
+            &ApplyOptions::default(),
+            &mut Vec::new(),
+        );
+        assert!(chapters.is_ok_and(|chapters| chapters[0].start_time == 30_000));
+
+        let options = ApplyOptions::default().with_intro_title("Cold open");
+        let chapters = process_chapters(records(), 120_000f64, &options, &mut Vec::new()).unwrap();
+        assert_eq!(chapters.len(), 3);
+        assert_eq!((chapters[0].start_time, chapters[0].end_time), (0, 30_000));
+        assert_eq!(chapter_title(&chapters[0]), "Cold open");
+        assert_eq!(chapter_title(&chapters[1]), "Interview");
+
+        //Nothing to cover when the first marker is at zero
+        let records = load_cvs(test_file!("valid_chaps.cvs").as_ref(), &options).unwrap();
+        let chapters = process_chapters(records, 120_000f64, &options, &mut Vec::new()).unwrap();
+        assert_eq!(chapters.len(), 2);
+    }
+
+    #[test]
+    fn test_late_first_chapter() {
+        let records = || {
+            load_cvs(
+                test_file!("late_start_chaps.cvs").as_ref(),
+                &ApplyOptions::default(),
+            )
+            .unwrap()
+        };
+        let mut warnings = Vec::new();
+        process_chapters(
+            records(),
+            120_000f64,
+            &ApplyOptions::default(),
+            &mut warnings,
+        )
+        .unwrap();
+        assert_eq!(
+            warnings,
+            [Warning::LateFirstChapter {
+                title: "Interview".to_string(),
+                start: 30_000,
+            }]
+        );
+
+        let options = ApplyOptions::default().with_strict_span(true);
+        let chapters = process_chapters(records(), 120_000f64, &options, &mut Vec::new());
+        assert!(chapters.is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::MarkersSpan(issue)) => issue.contains("\"Interview\""),
+            _ => false,
+        }));
+
+        //An intro covers the opening
+        let options = ApplyOptions::default().with_intro_title("Cold open");
+        let mut warnings = Vec::new();
+        process_chapters(records(), 120_000f64, &options, &mut warnings).unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_explicit_ids() {
+        let ids_dir = temp_dir().join("presquile_explicit_ids");
+        let _ = remove_dir_all(&ids_dir);
+        create_dir_all(&ids_dir).unwrap();
+        let new_mp3_file = apply_with_output(
+            test_file!("ids_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            ids_dir.join("episode.mp3"),
+            Mode::Sequential,
+        )
+        .unwrap();
+
+        let tag = Tag::read_from_path(new_mp3_file).unwrap();
+        let mut chapters: Vec<_> = tag.chapters().collect();
+        chapters.sort_by_key(|chapter| chapter.start_time);
+        let ids: Vec<_> = chapters
+            .iter()
+            .map(|chapter| chapter.element_id.as_str())
+            .collect();
+        assert_eq!(ids, ["intro", "interview-main", "chp003"]);
+        let toc = tag.tables_of_contents().next().unwrap();
+        assert_eq!(toc.elements, ids);
+
+        let duplicates = ids_dir.join("duplicate_ids.cvs");
+        let content = read_to_string(test_file!("ids_chaps.cvs")).unwrap();
+        write(&duplicates, content.replace("interview-main", "intro")).unwrap();
+        let chapters = parse_chapters(duplicates, 120_000f64);
+        assert!(chapters.is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::DuplicateId(id)) => id == "intro",
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn test_normalize_titles() {
+        let messy_dir = temp_dir().join("presquile_normalize_titles");
+        let _ = remove_dir_all(&messy_dir);
+        create_dir_all(&messy_dir).unwrap();
+        let messy = messy_dir.join("messy_chaps.cvs");
+        write(
+            &messy,
+            "Name\tStart\tDuration\tTime Format\tType\tDescription\n\
+             \u{a0} Intro\u{7}  and\u{a0}\u{a0}welcome \t0:00.000\t0:00.000\tdecimal\tCue\t\n\
+             Ad\u{1b}vertising\t1:00.636\t0:00.000\tdecimal\tCue\t\n",
+        )
+        .unwrap();
+        let titles = |options: &ApplyOptions| {
+            let records = load_cvs(&messy, options).unwrap();
+            records
+                .into_iter()
+                .map(|record| record.name)
+                .collect::<Vec<_>>()
+        };
+
+        let options = ApplyOptions::default().with_normalize_titles(true);
+        assert_eq!(titles(&options), ["Intro and welcome", "Advertising"]);
+        assert_eq!(titles(&ApplyOptions::default())[1], "Ad\u{1b}vertising");
+    }
+
+    #[test]
+    fn test_max_title_len() {
+        let long_dir = temp_dir().join("presquile_max_title_len");
+        let _ = remove_dir_all(&long_dir);
+        create_dir_all(&long_dir).unwrap();
+        let long = long_dir.join("long_chaps.cvs");
+        write(
+            &long,
+            "Name\tStart\tDuration\tTime Format\tType\tDescription\n\
+             Intro\t0:00.000\t0:00.000\tdecimal\tCue\t\n\
+             Interview with the guest about the new album\t0:30.000\t0:00.000\tdecimal\tCue\t\n",
+        )
+        .unwrap();
+        let warnings = |options: &ApplyOptions| {
+            let records = load_cvs(&long, options).unwrap();
+            let mut warnings = Vec::new();
+            process_chapters(records, 120_000f64, options, &mut warnings).unwrap();
+            warnings
+        };
+
+        assert!(warnings(&ApplyOptions::default()).is_empty());
+        let options = ApplyOptions::default().with_max_title_len(30);
+        assert_eq!(
+            warnings(&options),
+            [Warning::LongTitle {
+                title: "Interview with the guest about the new album".to_string(),
+                len: 44,
+                max_len: 30,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_start_offset() {
+        let records = || {
+            load_cvs(
+                test_file!("valid_chaps.cvs").as_ref(),
+                &ApplyOptions::default(),
+            )
+            .unwrap()
+        };
+        let starts = |options: &ApplyOptions, warnings: &mut Vec<Warning>| {
+            let chapters = process_chapters(records(), 120_000f64, options, warnings).unwrap();
+            chapters
+                .iter()
+                .map(|chapter| chapter.start_time)
+                .collect::<Vec<_>>()
+        };
+
+        let mut warnings = Vec::new();
+        let options = ApplyOptions::default().with_start_offset_ms(2_000);
+        assert_eq!(starts(&options, &mut warnings), [2_000, 62_636]);
+        assert_eq!(
+            warnings,
+            [Warning::LateFirstChapter {
+                title: "Intro".to_string(),
+                start: 2_000,
+            }]
+        );
+
+        //The intro would start before zero, the advertising covers the opening instead
+        let mut warnings = Vec::new();
+        let options = ApplyOptions::default().with_start_offset_ms(-61_000);
+        assert_eq!(starts(&options, &mut warnings), [0]);
+        assert_eq!(
+            warnings,
+            [Warning::DroppedBeforeStart {
+                title: "Intro".to_string()
+            }]
+        );
+
+        let mut warnings = Vec::new();
+        let options = ApplyOptions::default().with_start_offset_ms(60_000);
+        assert_eq!(starts(&options, &mut warnings), [60_000]);
+        assert!(matches!(
+            &warnings[..],
+            [
+                Warning::LateFirstChapter { start: 60_000, .. },
+                Warning::DroppedPastEnd { title, start: 120_000, .. },
+            ] if title == "Advertising"
+        ));
+    }
+
+    #[test]
+    fn test_gapless() {
+        let records = load_cvs(
+            test_file!("duration_chaps.cvs").as_ref(),
+            &ApplyOptions::default(),
+        )
+        .unwrap();
+        let options = ApplyOptions::default().with_gapless(true);
+        let chapters = process_chapters(records, 120_000f64, &options, &mut Vec::new()).unwrap();
+
+        assert_eq!(chapters.len(), 2);
+        assert!(chapters
+            .windows(2)
+            .all(|pair| pair[0].end_time == pair[1].start_time));
+        assert_eq!(chapters[0].start_time, 0);
+        assert_eq!(chapters.last().unwrap().end_time, 120_000);
+    }
+
+    #[test]
+    fn test_broken_time_parallel() {
+        assert!(apply(
+            test_file!("broken_time_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            Mode::Parallel,
+        )
+        .is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::InvalidRow(3, _)) => true,
+            _ => false,
+        }))
+    }
+
+    #[test]
+    fn test_broken_time_seq() {
+        assert!(apply(
+            test_file!("broken_time_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            Mode::Sequential,
+        )
+        .is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::InvalidRow(3, _)) => true,
+            _ => false,
+        }))
+    }
+
+    #[test]
+    fn test_too_many_time_fields_seq() {
+        assert!(apply(
+            test_file!("too_many_time_fields_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            Mode::Sequential,
+        )
+        .is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::InvalidRow(3, _)) => true,
+            _ => false,
+        }))
+    }
+
+    #[test]
+    fn test_custom_output() {
+        let output_dir = temp_dir().join("presquile_custom_output");
+        let _ = remove_dir_all(&output_dir);
+        let output = output_dir.join("nested").join("episode.mp3");
+
+        let new_mp3_file = apply_with_output(
+            test_file!("valid_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            output.clone(),
+            Mode::Sequential,
+        );
+        assert!(new_mp3_file.is_ok_and(|path| path == output));
+
+        let tag = Tag::read_from_path(&output);
+        assert!(tag.is_ok_and(|tag| tag.chapters().count() == 2));
+    }
+
+    #[test]
+    fn test_preserve_existing_tag() {
+        let source_dir = temp_dir().join("presquile_preserve_tag");
+        let _ = remove_dir_all(&source_dir);
+        create_dir_all(&source_dir).unwrap();
+        let source = source_dir.join("audio.mp3");
+        copy(test_file!("audio.mp3"), &source).unwrap();
+
+        let mut tag = Tag::read_from_path(&source).unwrap();
+        tag.set_title("Episode 1");
+        tag.set_artist("Presquile");
+        tag.write_to_path(&source, Version::Id3v24).unwrap();
+
+        let new_mp3_file = apply(
+            test_file!("valid_chaps.cvs").into(),
+            source,
+            Mode::Sequential,
+        );
+        assert!(new_mp3_file.is_ok());
+
+        let tag = Tag::read_from_path(new_mp3_file.unwrap()).unwrap();
+        assert_eq!(tag.title(), Some("Episode 1"));
+        assert_eq!(tag.artist(), Some("Presquile"));
+
+        let chapter_ids: Vec<_> = tag
+            .chapters()
+            .map(|chap| chap.element_id.as_str())
+            .collect();
+        assert_eq!(chapter_ids, ["chp001", "chp002"]);
+        assert_eq!(tag.tables_of_contents().count(), 1);
+    }
+
+    #[test]
+    fn test_flac_seq() {
+        let source_dir = temp_dir().join("presquile_flac_seq");
+        let _ = remove_dir_all(&source_dir);
+        create_dir_all(&source_dir).unwrap();
+        let source = source_dir.join("audio.flac");
+        copy(test_file!("audio.flac"), &source).unwrap();
+
+        let new_flac_file = apply(
+            test_file!("valid_chaps.cvs").into(),
+            source,
+            Mode::Sequential,
+        );
+        assert!(new_flac_file.is_ok());
+
+        let tag = metaflac::Tag::read_from_path(new_flac_file.unwrap()).unwrap();
+        let comments = tag.vorbis_comments().unwrap();
+        assert_eq!(comments.get("CHAPTER001").unwrap(), &["00:00:00.000"]);
+        assert_eq!(comments.get("CHAPTER001NAME").unwrap(), &["Intro"]);
+        assert_eq!(comments.get("CHAPTER002").unwrap(), &["00:01:00.636"]);
+        assert_eq!(comments.get("CHAPTER002NAME").unwrap(), &["Advertising"]);
+    }
+
+    #[test]
+    fn test_flac_parallel() {
+        let new_flac_file = apply_with_output(
+            test_file!("valid_chaps.cvs").into(),
+            test_file!("audio.flac").into(),
+            temp_dir().join("presquile_flac_parallel.flac"),
+            Mode::Parallel,
+        );
+        assert!(new_flac_file.is_ok());
+
+        let tag = metaflac::Tag::read_from_path(new_flac_file.unwrap()).unwrap();
+        let comments = tag.vorbis_comments().unwrap();
+        assert_eq!(comments.get("CHAPTER001NAME").unwrap(), &["Intro"]);
+        assert_eq!(comments.get("CHAPTER002NAME").unwrap(), &["Advertising"]);
+    }
+
+    #[test]
+    fn test_m4b_seq() {
+        let source_dir = temp_dir().join("presquile_m4b_seq");
+        let _ = remove_dir_all(&source_dir);
+        create_dir_all(&source_dir).unwrap();
+        let source = source_dir.join("audio.m4b");
+        copy(test_file!("audio.m4b"), &source).unwrap();
+
+        let new_m4b_file = apply(
+            test_file!("valid_chaps.cvs").into(),
+            source,
+            Mode::Sequential,
+        );
+        assert!(new_m4b_file.is_ok());
+
+        let tag = mp4ameta::Tag::read_from_path(new_m4b_file.unwrap()).unwrap();
+        let chapters: Vec<_> = tag
+            .chapter_list()
+            .iter()
+            .map(|chapter| (chapter.start.as_millis(), chapter.title.as_str()))
+            .collect();
+        assert_eq!(chapters, [(0, "Intro"), (60_636, "Advertising")]);
+    }
+
+    #[test]
+    fn test_parse_chapters() {
+        let chapters = parse_chapters(test_file!("valid_chaps.cvs").into(), 120_000f64);
+        assert!(chapters.is_ok());
+
+        let chapters: Vec<_> = chapters
+            .unwrap()
+            .into_iter()
+            .map(|chap| (chap.element_id, chap.start_time, chap.end_time))
+            .collect();
+        assert_eq!(
+            chapters,
+            [
+                ("chp001".to_string(), 0, 60_636),
+                ("chp002".to_string(), 60_636, 120_000)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_cvs_from_reader() {
+        let expected = load_cvs(
+            test_file!("valid_chaps.cvs").as_ref(),
+            &ApplyOptions::default(),
+        )
+        .unwrap();
+        let records =
+            load_cvs_from_reader(Cursor::new(include_bytes!(test_file!("valid_chaps.cvs"))));
+        assert!(records.is_ok_and(|records| records == expected));
+    }
+
+    #[test]
+    fn test_not_cvs_from_reader() {
+        assert!(load_cvs_from_reader(&b"file contents"[..]).is_err_and(|e| {
+            match e.downcast_ref() {
+                Some(AppliersErrors::ChaptersFileNotCompatible) => true,
+                _ => false,
+            }
+        }))
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(0), "00:00:00.000");
+        assert_eq!(format_time(3_723_456), "01:02:03.456");
+        assert_eq!(convert_time(&format_time(60_636)).unwrap(), 60_636);
+    }
+
+    #[test]
+    fn test_strip() {
+        let source_dir = temp_dir().join("presquile_strip");
+        let _ = remove_dir_all(&source_dir);
+        create_dir_all(&source_dir).unwrap();
+        let source = source_dir.join("audio.mp3");
+        copy(test_file!("audio.mp3"), &source).unwrap();
+
+        let mut tag = Tag::read_from_path(&source).unwrap();
+        tag.set_title("Episode 1");
+        tag.write_to_path(&source, Version::Id3v24).unwrap();
+
+        let new_mp3_file = apply(
+            test_file!("valid_chaps.cvs").into(),
+            source,
+            Mode::Sequential,
+        )
+        .unwrap();
+        let stripped = strip(new_mp3_file);
+        assert!(stripped.is_ok());
+        let stripped = stripped.unwrap();
+        assert!(stripped.is_some());
+
+        let tag = Tag::read_from_path(stripped.as_ref().unwrap()).unwrap();
+        assert_eq!(tag.chapters().count(), 0);
+        assert_eq!(tag.tables_of_contents().count(), 0);
+        assert_eq!(tag.title(), Some("Episode 1"));
+
+        assert!(strip(stripped.unwrap()).is_ok_and(|path| path.is_none()));
+    }
+
+    #[test]
+    fn test_strip_with_options() {
+        let source_dir = temp_dir().join("presquile_strip_options");
+        let _ = remove_dir_all(&source_dir);
+        create_dir_all(&source_dir).unwrap();
+        let source = source_dir.join("audio.mp3");
+        copy(test_file!("audio.mp3"), &source).unwrap();
+
+        let options = ApplyOptions::default().with_suffix("_clean");
+        let stripped = strip_with_options(source.clone(), &options).unwrap();
+        assert_eq!(stripped, Some(source_dir.join("audio_clean.mp3")));
+
+        let output_dir = source_dir.join("out");
+        let options = options.with_output_dir(&output_dir);
+        let stripped = strip_with_options(source.clone(), &options).unwrap();
+        assert_eq!(stripped, Some(output_dir.join("audio_clean.mp3")));
+
+        let options = ApplyOptions::default().with_in_place(true);
+        let stripped = strip_with_options(source.clone(), &options).unwrap();
+        assert_eq!(stripped.as_ref(), Some(&source));
+        let tag = Tag::read_from_path(&source).unwrap();
+        assert_eq!(tag.chapters().count(), 0);
+        assert_eq!(staged_files(&source), Vec::<PathBuf>::new());
+    }
+
+    #[test]
+    fn test_strip_keep_existing() {
+        let source_dir = temp_dir().join("presquile_strip_keep_existing");
+        let _ = remove_dir_all(&source_dir);
+        create_dir_all(&source_dir).unwrap();
+        let source = source_dir.join("audio.mp3");
+        copy(test_file!("audio.mp3"), &source).unwrap();
+        let output = source_dir.join("audio_enriched.mp3");
+        write(&output, "edited by hand").unwrap();
+
+        let options = ApplyOptions::default().with_keep_existing(true);
+        assert!(
+            strip_with_options(source.clone(), &options).is_err_and(|e| match e.downcast_ref() {
+                Some(AppliersErrors::OutputExists(path)) => path == &output.display().to_string(),
+                _ => false,
+            })
+        );
+        assert_eq!(read_to_string(&output).unwrap(), "edited by hand");
+
+        let stripped = strip_with_options(source, &ApplyOptions::default()).unwrap();
+        assert_eq!(stripped.as_ref(), Some(&output));
+        assert_eq!(Tag::read_from_path(&output).unwrap().chapters().count(), 0);
+    }
+
+    #[test]
+    fn test_unsorted_markers_strict() {
+        let options = ApplyOptions {
+            strict_order: true,
+            ..Default::default()
+        };
+        assert!(apply_with_options(
+            test_file!("unsorted_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            Mode::Sequential,
+            options,
+        )
+        .is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::UnsortedMarkers(row, start, previous)) => {
+                (*row, start.as_str(), previous.as_str()) == (3, "0:30.000", "1:00.636")
+            }
+            _ => false,
+        }))
+    }
+
+    #[test]
+    fn test_unsorted_markers_sorted() {
+        let records = load_cvs(
+            test_file!("reversed_chaps.cvs").as_ref(),
+            &ApplyOptions::default(),
+        );
+        assert!(records.is_ok());
+
+        let chapters: Vec<_> = build_chapters(records.unwrap(), 120_000f64)
+            .unwrap()
+            .into_iter()
+            .map(|chap| (chap.start_time, chap.end_time))
+            .collect();
+        assert_eq!(chapters, [(0, 30_000), (30_000, 60_636), (60_636, 120_000)]);
+    }
+
+    #[test]
+    fn test_overlapping_chapters() {
+        assert!(
+            parse_chapters(test_file!("overlapping_chaps.cvs").into(), 120_000f64).is_err_and(
+                |e| match e.downcast_ref() {
+                    Some(AppliersErrors::OverlappingChapters(first, second)) => {
+                        (first.as_str(), second.as_str()) == ("Intro", "Advertising")
+                    }
+                    _ => false,
+                }
+            )
+        )
+    }
+
+    #[test]
+    fn test_min_duration_default() {
+        let chapters = parse_chapters(test_file!("valid_chaps.cvs").into(), 120_000f64).unwrap();
+        let expected = chapters.clone();
+        let chapters = enforce_min_duration(chapters, &ApplyOptions::default());
+        assert!(chapters.is_ok_and(|chapters| chapters == expected));
+    }
+
+    #[test]
+    fn test_min_duration_reject() {
+        let chapters = parse_chapters(test_file!("valid_chaps.cvs").into(), 120_000f64).unwrap();
+        let options = ApplyOptions {
+            min_chapter_duration: 60_000,
+            ..Default::default()
+        };
+        assert!(
+            enforce_min_duration(chapters, &options).is_err_and(|e| match e.downcast_ref() {
+                Some(AppliersErrors::ChapterTooShort(title, duration)) => {
+                    (title.as_str(), *duration) == ("Advertising", 59_364)
+                }
+                _ => false,
+            })
+        )
+    }
+
+    #[test]
+    fn test_min_duration_merge() {
+        let chapters = parse_chapters(test_file!("valid_chaps.cvs").into(), 120_000f64).unwrap();
+        let options = ApplyOptions {
+            min_chapter_duration: 60_000,
+            merge_short_chapters: true,
+            ..Default::default()
+        };
+        let chapters: Vec<_> = enforce_min_duration(chapters, &options)
+            .unwrap()
+            .into_iter()
+            .map(|chap| (chap.element_id, chap.start_time, chap.end_time))
+            .collect();
+        assert_eq!(chapters, [("chp001".to_string(), 0, 120_000)]);
+    }
+
+    #[test]
+    fn test_clamp_to_duration() {
+        let chapters: Vec<_> =
+            parse_chapters(test_file!("out_of_range_chaps.cvs").into(), 120_000f64)
+                .unwrap()
+                .into_iter()
+                .map(|chap| (chap.start_time, chap.end_time))
+                .collect();
+        assert_eq!(chapters, [(0, 60_636), (60_636, 120_000)]);
+    }
+
+    #[test]
+    fn test_convert_time_without_fraction() {
+        assert_eq!(convert_time("00:01:30").unwrap(), 90_000);
+        assert_eq!(convert_time("1:02:03").unwrap(), 3_723_000);
+        assert_eq!(convert_time("90.250").unwrap(), 90_250);
+
+        let chapters: Vec<_> =
+            parse_chapters(test_file!("no_fraction_chaps.cvs").into(), 120_000f64)
+                .unwrap()
+                .into_iter()
+                .map(|chap| (chap.start_time, chap.end_time))
+                .collect();
+        assert_eq!(chapters, [(0, 90_000), (90_000, 120_000)]);
+    }
+
+    #[test]
+    fn test_custom_columns() {
+        let file = test_file!("custom_columns_chaps.cvs");
+        let mut warnings = Vec::new();
+        let options = ApplyOptions::default()
+            .with_name_column("Title")
+            .with_start_column("Offset")
+            .with_strict_columns(true);
+        let records = load_cvs_reporting(file.as_ref(), &options, &mut warnings).unwrap();
+        let titles: Vec<_> = records.iter().map(|record| record.name.as_str()).collect();
+        assert_eq!(titles, ["Intro", "Advertising"]);
+        assert_eq!(records[1].start, "01:00.636");
+        //The regular Name column is left out in favour of the custom one
+        assert_eq!(
+            warnings,
+            [Warning::UnexpectedColumn {
+                column: "Name".to_string()
+            }]
+        );
+
+        assert!(
+            load_cvs(file.as_ref(), &ApplyOptions::default()).is_err_and(|e| {
+                match e.downcast_ref() {
+                    Some(AppliersErrors::ChaptersFileNotCompatible) => true,
+                    _ => false,
+                }
+            })
+        );
+        let options = ApplyOptions::default().with_start_column("Missing");
+        assert!(load_cvs(test_file!("valid_chaps.cvs").as_ref(), &options).is_err());
+    }
+
+    #[test]
+    fn test_convert_time_seconds() {
+        assert_eq!(convert_time("93.5").unwrap(), 93_500);
+        assert_eq!(convert_time("0").unwrap(), 0);
+        assert_eq!(convert_time("3600").unwrap(), 3_600_000);
+        assert!(convert_time("93.5s").is_err());
+        //Plain seconds overflow the milliseconds as soon as the colon separated form does
+        assert_eq!(convert_time("4294967.295").unwrap(), u32::MAX);
+        for overflowing in ["4294967.296", "5000000", "99999999999"] {
+            assert!(
+                convert_time(overflowing).is_err_and(|e| match e.downcast_ref() {
+                    Some(AppliersErrors::ChaptersFileNotCompatible) => true,
+                    _ => false,
+                })
+            );
+        }
+
+        let chapters: Vec<_> = parse_chapters(test_file!("seconds_chaps.cvs").into(), 4_000_000f64)
+            .unwrap()
+            .into_iter()
+            .map(|chap| (chap.start_time, chap.end_time))
+            .collect();
+        assert_eq!(
+            chapters,
+            [(0, 93_500), (93_500, 3_600_000), (3_600_000, 4_000_000)]
+        );
+    }
+
+    #[test]
+    fn test_convert_time_overflow() {
+        let overflows = |time: &str| {
+            convert_time(time).is_err_and(|e| match e.downcast_ref() {
+                Some(AppliersErrors::ChaptersFileNotCompatible) => true,
+                _ => false,
+            })
+        };
+        assert!(overflows("1200:00:00"));
+        assert!(overflows("1193:02:47.296"));
+        assert_eq!(convert_time("1193:02:47.295").unwrap(), u32::MAX);
+    }
+
+    #[test]
+    fn test_convert_time_fraction_precision() {
+        assert_eq!(convert_time("0:01.5").unwrap(), 1_500);
+        assert_eq!(convert_time("0:01.50").unwrap(), 1_500);
+        assert_eq!(convert_time("0:01.500").unwrap(), 1_500);
+        assert_eq!(convert_time("0:01.05").unwrap(), 1_050);
+        assert_eq!(convert_time("0:01.5009").unwrap(), 1_500);
+        assert_eq!(convert_time("1:30.5").unwrap(), 90_500);
+    }
+
+    #[test]
+    fn test_comma_decimal_separator() {
+        assert_eq!(convert_time("00:01:30,500").unwrap(), 90_500);
+
+        let expected = parse_chapters(test_file!("valid_chaps.cvs").into(), 120_000f64).unwrap();
+        let chapters = parse_chapters(test_file!("comma_decimal_chaps.cvs").into(), 120_000f64);
+        assert!(chapters.is_ok_and(|chapters| chapters == expected));
+    }
+
+    #[test]
+    fn verify_valid_chaps() {
+        let report = verify(
+            test_file!("valid_chaps.cvs").into(),
+            &ApplyOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(report.chapters, 2);
+        assert_eq!(report.span, 60_636);
+        assert!(report.warnings.is_empty());
+    }
+
+    #[test]
+    fn verify_explicit_duration_span() {
+        let report = verify(
+            test_file!("duration_chaps.cvs").into(),
+            &ApplyOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(report.chapters, 2);
+        assert_eq!(report.span, 81_136);
+    }
+
+    #[test]
+    fn verify_overlapping_chaps() {
+        assert!(verify(
+            test_file!("overlapping_chaps.cvs").into(),
+            &ApplyOptions::default()
+        )
+        .is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::OverlappingChapters(_, _)) => true,
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn test_sidecar() {
+        let sidecar_dir = temp_dir().join("presquile_sidecar");
+        let _ = remove_dir_all(&sidecar_dir);
+        create_dir_all(&sidecar_dir).unwrap();
+        let mp3_file = sidecar_dir.join("episode.mp3");
+        copy(test_file!("audio.mp3"), &mp3_file).unwrap();
+
+        let output = apply_with_options(
+            test_file!("valid_chaps.cvs").into(),
+            mp3_file.clone(),
+            Mode::Parallel,
+            ApplyOptions::default().with_sidecar(true),
+        )
+        .unwrap();
+        assert_eq!(output, sidecar_dir.join("episode.chapters.txt"));
+        assert_eq!(
+            read_to_string(output).unwrap(),
+            "00:00:00.000 Intro\n00:01:00.636 Advertising\n"
+        );
+        assert_eq!(
+            read(&mp3_file).unwrap(),
+            read(test_file!("audio.mp3")).unwrap()
+        );
+        assert_eq!(read_dir(&sidecar_dir).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_dry_run() {
+        let output_dir = temp_dir().join("presquile_dry_run");
+        let _ = remove_dir_all(&output_dir);
+        let output = output_dir.join("episode.mp3");
+
+        for mode in [Mode::Sequential, Mode::Parallel] {
+            let options = ApplyOptions {
+                output: Some(output.clone()),
+                dry_run: true,
+                ..Default::default()
+            };
+            let new_mp3_file = apply_with_options(
+                test_file!("valid_chaps.cvs").into(),
+                test_file!("audio.mp3").into(),
+                mode,
+                options,
+            );
+            assert!(new_mp3_file.is_ok_and(|path| path == output));
+            assert!(!output_dir.exists());
+        }
+
+        let report = apply_reported(
+            test_file!("valid_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            Mode::Sequential,
+            ApplyOptions::default()
+                .with_output(&output)
+                .with_dry_run(true),
+        )
+        .unwrap();
+        assert_eq!(report.preview.len(), report.chapter_count);
+        assert_eq!(
+            report.preview[0],
+            "chp001\t00:00:00.000 --> 00:01:00.636\tIntro"
+        );
+        assert!(!output_dir.exists());
+    }
+
+    #[test]
+    fn test_in_place() {
+        let source_dir = temp_dir().join("presquile_in_place");
+        let _ = remove_dir_all(&source_dir);
+        create_dir_all(&source_dir).unwrap();
+        let source = source_dir.join("audio.mp3");
+        copy(test_file!("audio.mp3"), &source).unwrap();
+        //A copy renamed over the source leaves the other link to the original untouched
+        let link_dir = temp_dir().join("presquile_in_place_link");
+        let _ = remove_dir_all(&link_dir);
+        create_dir_all(&link_dir).unwrap();
+        let link = link_dir.join("audio.mp3");
+        hard_link(&source, &link).unwrap();
+
+        let options = ApplyOptions {
+            in_place: true,
+            ..Default::default()
+        };
+        let new_mp3_file = apply_with_options(
+            test_file!("unsorted_chaps.cvs").into(),
+            source.clone(),
+            Mode::Sequential,
+            options,
+        );
+        assert!(new_mp3_file.is_ok_and(|path| path == source));
+        assert_eq!(read_dir(&source_dir).unwrap().count(), 1);
+
+        let tag = Tag::read_from_path(&source);
+        assert!(tag.is_ok_and(|tag| tag.chapters().count() == 3));
+        assert_eq!(read(&link).unwrap(), read(test_file!("audio.mp3")).unwrap());
+    }
+
+    #[test]
+    fn test_in_place_backup() {
+        let source_dir = temp_dir().join("presquile_in_place_backup");
+        let _ = remove_dir_all(&source_dir);
+        create_dir_all(&source_dir).unwrap();
+        let source = source_dir.join("audio.mp3");
+        copy(test_file!("audio.mp3"), &source).unwrap();
+
+        let options = ApplyOptions::default()
+            .with_in_place(true)
+            .with_backup(true);
+        let new_mp3_file = apply_with_options(
+            test_file!("unsorted_chaps.cvs").into(),
+            source.clone(),
+            Mode::Parallel,
+            options,
+        );
+        assert!(new_mp3_file.is_ok_and(|path| path == source));
+        assert_eq!(read_dir(&source_dir).unwrap().count(), 2);
+
+        let backup = read(backup_path(&source)).unwrap();
+        assert_eq!(backup, read(test_file!("audio.mp3")).unwrap());
+        let tag = Tag::read_from_path(&source);
+        assert!(tag.is_ok_and(|tag| tag.chapters().count() == 3));
+    }
+
+    #[test]
+    fn test_enriched_path() {
+        let file = Path::new("/tmp/episode.mp3");
+        assert_eq!(
+            enriched_path(file, "_enriched").unwrap(),
+            Path::new("/tmp/episode_enriched.mp3")
+        );
+        assert_eq!(
+            enriched_path(Path::new("/tmp/episode_enriched.mp3"), "_enriched").unwrap(),
+            Path::new("/tmp/episode_enriched.mp3")
+        );
+        assert_eq!(
+            enriched_path(file, ".chapters").unwrap(),
+            Path::new("/tmp/episode.chapters.mp3")
+        );
+    }
+
+    #[test]
+    fn test_custom_suffix_rerun() {
+        let source_dir = temp_dir().join("presquile_custom_suffix");
+        let _ = remove_dir_all(&source_dir);
+        create_dir_all(&source_dir).unwrap();
+        let source = source_dir.join("audio.mp3");
+        copy(test_file!("audio.mp3"), &source).unwrap();
+
+        let options = ApplyOptions {
+            suffix: Some("_chapters".to_string()),
+            ..Default::default()
+        };
+        let new_mp3_file = apply_with_options(
+            test_file!("valid_chaps.cvs").into(),
+            source,
+            Mode::Sequential,
+            options.clone(),
+        )
+        .unwrap();
+        assert_eq!(new_mp3_file, source_dir.join("audio_chapters.mp3"));
+
+        let rerun = apply_with_options(
+            test_file!("unsorted_chaps.cvs").into(),
+            new_mp3_file.clone(),
+            Mode::Sequential,
+            options,
+        );
+        assert!(rerun.is_ok_and(|path| path == new_mp3_file));
+        assert_eq!(read_dir(&source_dir).unwrap().count(), 2);
+
+        let tag = Tag::read_from_path(&new_mp3_file);
+        assert!(tag.is_ok_and(|tag| tag.chapters().count() == 3));
+    }
+
+    #[test]
+    fn test_atomic_write() {
+        let output_dir = temp_dir().join("presquile_atomic_write");
+        let _ = remove_dir_all(&output_dir);
+        create_dir_all(&output_dir).unwrap();
+        let output = output_dir.join("episode.mp3");
+        copy(test_file!("audio.mp3"), &output).unwrap();
+
+        //A write interrupted halfway keeps the previous file intact
+        let staged = staging_path(&output).unwrap();
+        copy(test_file!("audio.mp3"), &staged).unwrap();
+        let interrupted = publish(&staged, &output, |file| {
+            File::create(file)?;
+            anyhow::bail!("interrupted")
+        });
+        assert!(interrupted.is_err());
+        assert!(!staged.exists());
+        assert_eq!(
+            read(&output).unwrap(),
+            read(test_file!("audio.mp3")).unwrap()
+        );
+
+        let new_mp3_file = apply_with_output(
+            test_file!("unsorted_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            output.clone(),
+            Mode::Parallel,
+        );
+        assert!(new_mp3_file.is_ok_and(|path| path == output));
+        assert_eq!(read_dir(&output_dir).unwrap().count(), 1);
+
+        let tag = Tag::read_from_path(&output);
+        assert!(tag.is_ok_and(|tag| tag.chapters().count() == 3));
+    }
+
+    /// Staged copies left next to `output`
+    fn staged_files(output: &Path) -> Vec<PathBuf> {
+        read_dir(output.parent().unwrap())
+            .into_iter()
+            .flatten()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                path.file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with(".presquile-"))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_concurrent_staging() {
+        let output_dir = temp_dir().join("presquile_concurrent_staging");
+        let _ = remove_dir_all(&output_dir);
+        let output = output_dir.join("episode.mp3");
+
+        let first = staging_path(&output).unwrap();
+        let second = staging_path(&output).unwrap();
+        assert_ne!(first, second);
+        assert!(first.exists() && second.exists());
+        remove_file(first).unwrap();
+        remove_file(second).unwrap();
+
+        //Two applies to the same output each assemble their own copy
+        thread::scope(|s| {
+            let applies: Vec<_> = [Mode::Sequential, Mode::Parallel]
+                .into_iter()
+                .map(|mode| {
+                    let output = output.clone();
+                    s.spawn(move || {
+                        apply_with_output(
+                            test_file!("valid_chaps.cvs").into(),
+                            test_file!("audio.mp3").into(),
+                            output,
+                            mode,
+                        )
+                    })
+                })
+                .collect();
+            for apply in applies {
+                assert!(apply.join().unwrap().is_ok());
+            }
+        });
+        assert!(Tag::read_from_path(&output).is_ok_and(|tag| tag.chapters().count() == 2));
+        assert!(staged_files(&output).is_empty());
+    }
+
+    #[test]
+    fn test_failed_apply_leaves_no_staged_file() {
+        let output_dir = temp_dir().join("presquile_failed_apply");
+        let _ = remove_dir_all(&output_dir);
+        let output = output_dir.join("episode.mp3");
+
+        let options = ApplyOptions {
+            output: Some(output.clone()),
+            ..Default::default()
+        };
+        let new_mp3_file = apply_with_options(
+            test_file!("overlapping_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            Mode::Parallel,
+            options,
+        );
+        assert!(new_mp3_file.is_err());
+        assert!(!output.exists());
+        assert!(staged_files(&output).is_empty());
+    }
+
+    #[test]
+    fn test_batch_output_dir() {
+        let batch_dir = temp_dir().join("presquile_batch_output_dir");
+        let _ = remove_dir_all(&batch_dir);
+        create_dir_all(&batch_dir).unwrap();
+        copy(test_file!("audio.mp3"), batch_dir.join("first.mp3")).unwrap();
+        copy(test_file!("valid_chaps.cvs"), batch_dir.join("first.cvs")).unwrap();
+        let output_dir = batch_dir.join("out").join("enriched");
+
+        let options = ApplyOptions::default().with_output_dir(&output_dir);
+        let results = batch_with_options(batch_dir.clone(), Mode::Parallel, &options, |_, _| {});
+        let output = output_dir.join("first_enriched.mp3");
+        assert!(results.is_ok_and(|results| *results[0].1.as_ref().unwrap() == output));
+        assert!(Tag::read_from_path(&output).is_ok_and(|tag| tag.chapters().count() == 2));
+        assert!(!batch_dir.join("first_enriched.mp3").exists());
+
+        let options = options.with_output(batch_dir.join("episode.mp3"));
+        let new_mp3_file = apply_with_options(
+            test_file!("valid_chaps.cvs").into(),
+            batch_dir.join("first.mp3"),
+            Mode::Sequential,
+            options,
+        );
+        assert!(new_mp3_file.is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::OutputConflict) => true,
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn test_same_path() {
+        let is_same_path = |result: anyhow::Result<PathBuf>| {
+            result.is_err_and(|e| match e.downcast_ref() {
+                Some(AppliersErrors::SamePath(_)) => true,
+                _ => false,
+            })
+        };
+        let output = temp_dir().join("presquile_same_path.mp3");
+        let _ = remove_file(&output);
+        let options = ApplyOptions::default().with_output(&output);
+        assert!(is_same_path(apply_with_options(
+            test_file!("audio.mp3").into(),
+            test_file!("audio.mp3").into(),
+            Mode::Sequential,
+            options.clone(),
+        )));
+        //Told apart after canonicalization, not by their spelling
+        let spelled = Path::new(test_file!("")).join("../test/audio.mp3");
+        assert!(is_same_path(apply_with_options(
+            test_file!("audio.mp3").into(),
+            spelled,
+            Mode::Parallel,
+            options,
+        )));
+        assert!(!output.exists());
+    }
+
+    #[test]
+    fn test_expand_patterns() {
+        let dir = temp_dir().join("presquile_expand_patterns");
+        let _ = remove_dir_all(&dir);
+        create_dir_all(&dir).unwrap();
+        for file in ["b.mp3", "a.mp3", "[x].mp3", "notes.txt"] {
+            write(dir.join(file), "").unwrap();
+        }
+        let expanded = expand_patterns(vec![dir.join("*.mp3"), dir.join("notes.txt")]).unwrap();
+        assert_eq!(
+            expanded,
+            [
+                dir.join("[x].mp3"),
+                dir.join("a.mp3"),
+                dir.join("b.mp3"),
+                dir.join("notes.txt")
+            ]
+        );
+        //A file named like a pattern is taken literally
+        let literal = expand_patterns(vec![dir.join("[x].mp3")]).unwrap();
+        assert_eq!(literal, [dir.join("[x].mp3")]);
+        assert!(
+            expand_patterns(vec![dir.join("*.flac")]).is_err_and(|e| match e.downcast_ref() {
+                Some(AppliersErrors::NoMatch(_)) => true,
+                _ => false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_batch_incremental() {
+        let batch_dir = temp_dir().join("presquile_batch_incremental");
+        let _ = remove_dir_all(&batch_dir);
+        create_dir_all(&batch_dir).unwrap();
+        copy(test_file!("audio.mp3"), batch_dir.join("first.mp3")).unwrap();
+        copy(test_file!("valid_chaps.cvs"), batch_dir.join("first.cvs")).unwrap();
+        let output = batch_dir.join("out").join("first_enriched.mp3");
+        let options = ApplyOptions::default()
+            .with_output_dir(batch_dir.join("out"))
+            .with_incremental(true);
+        let run = || batch_with_options(batch_dir.clone(), Mode::Sequential, &options, |_, _| {});
+
+        assert!(run().is_ok_and(|results| *results[0].1.as_ref().unwrap() == output));
+        assert!(run().is_ok_and(|results| match results[0].1 {
+            Err(ref e) => matches!(e.downcast_ref(), Some(AppliersErrors::UpToDate(_))),
+            Ok(_) => false,
+        }));
+
+        //Inputs edited after the last run bring the file back in
+        set_file_mtime(&output, FileTime::from_unix_time(1_600_000_000, 0)).unwrap();
+        assert!(run().is_ok_and(|results| results[0].1.is_ok()));
+    }
+
+    #[test]
+    fn test_apply_variants() {
+        let variants_dir = temp_dir().join("presquile_variants");
+        let _ = remove_dir_all(&variants_dir);
+        create_dir_all(&variants_dir).unwrap();
+        let variants = vec![test_file!("audio.mp3").into(), test_file!("cbr.mp3").into()];
+
+        let options = ApplyOptions::default().with_output_dir(&variants_dir);
+        let outputs = apply_variants(
+            test_file!("valid_chaps.cvs").into(),
+            variants.clone(),
+            Mode::Sequential,
+            options.clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            outputs,
+            [
+                variants_dir.join("audio_enriched.mp3"),
+                variants_dir.join("cbr_enriched.mp3")
+            ]
+        );
+        //The short variant ends before the second marker
+        let tags: Vec<_> = outputs
+            .iter()
+            .map(|output| Tag::read_from_path(output).unwrap())
+            .collect();
+        assert_eq!(tags[0].chapters().count(), 2);
+        assert_eq!(tags[1].chapters().count(), 1);
+        let end = |tag: &Tag| tag.chapters().map(|chapter| chapter.end_time).max();
+        assert!(end(&tags[1]) < end(&tags[0]));
+
+        let outputs = apply_variants(
+            test_file!("valid_chaps.cvs").into(),
+            variants,
+            Mode::Sequential,
+            options.with_output(variants_dir.join("episode.mp3")),
+        );
+        assert!(outputs.is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::SharedOutput(2)) => true,
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn test_batch_glob() {
+        let glob_dir = temp_dir().join("presquile_batch_glob");
+        let _ = remove_dir_all(&glob_dir);
+        create_dir_all(glob_dir.join("episodes")).unwrap();
+        for episode in ["first", "second"] {
+            let episode = glob_dir.join("episodes").join(episode);
+            copy(test_file!("audio.mp3"), episode.with_extension("mp3")).unwrap();
+            copy(test_file!("valid_chaps.cvs"), episode.with_extension("cvs")).unwrap();
+        }
+        //Outside of the pattern, left alone
+        copy(test_file!("audio.mp3"), glob_dir.join("third.mp3")).unwrap();
+
+        let pattern = glob_dir.join("episodes").join("*.mp3");
+        let options = ApplyOptions::default();
+        let results = batch_glob(
+            pattern.to_str().unwrap(),
+            Mode::Sequential,
+            &options,
+            |_, _| {},
+        )
+        .unwrap();
+        let files: Vec<_> = results.iter().map(|(file, _)| file.clone()).collect();
+        assert_eq!(
+            files,
+            [
+                glob_dir.join("episodes").join("first.mp3"),
+                glob_dir.join("episodes").join("second.mp3")
+            ]
+        );
+        for (_, output) in results {
+            let tag = Tag::read_from_path(output.unwrap());
+            assert!(tag.is_ok_and(|tag| tag.chapters().count() == 2));
+        }
+
+        let pattern = glob_dir.join("*.flac");
+        let results = batch_glob(
+            pattern.to_str().unwrap(),
+            Mode::Sequential,
+            &options,
+            |_, _| {},
+        );
+        assert!(results.is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::NoMatch(_)) => true,
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn test_batch() {
+        let batch_dir = temp_dir().join("presquile_batch");
+        let _ = remove_dir_all(&batch_dir);
+        create_dir_all(&batch_dir).unwrap();
+        copy(test_file!("audio.mp3"), batch_dir.join("first.mp3")).unwrap();
+        copy(test_file!("valid_chaps.cvs"), batch_dir.join("first.cvs")).unwrap();
+        copy(test_file!("audio.mp3"), batch_dir.join("second.mp3")).unwrap();
+        copy(
+            test_file!("unsorted_chaps.cvs"),
+            batch_dir.join("second.csv"),
+        )
+        .unwrap();
+        copy(test_file!("audio.mp3"), batch_dir.join("orphan.mp3")).unwrap();
+
+        for mode in [Mode::Sequential, Mode::Parallel] {
+            let results = batch(batch_dir.clone(), mode).unwrap();
+            let files: Vec<_> = results.iter().map(|(file, _)| file.clone()).collect();
+            assert_eq!(
+                files,
+                [
+                    batch_dir.join("first.mp3"),
+                    batch_dir.join("orphan.mp3"),
+                    batch_dir.join("second.mp3")
+                ]
+            );
+            assert!(results[1]
+                .1
+                .as_ref()
+                .is_err_and(|e| match e.downcast_ref() {
+                    Some(AppliersErrors::MissingCounterpart(_)) => true,
+                    _ => false,
+                }));
+
+            let tag = Tag::read_from_path(results[0].1.as_ref().unwrap());
+            assert!(tag.is_ok_and(|tag| tag.chapters().count() == 2));
+            let tag = Tag::read_from_path(results[2].1.as_ref().unwrap());
+            assert!(tag.is_ok_and(|tag| tag.chapters().count() == 3));
+        }
+    }
+
+    #[test]
+    fn test_apply_reported() {
+        let options = ApplyOptions {
+            output: Some(temp_dir().join("presquile_reported.mp3")),
+            ..Default::default()
+        };
+        let report = apply_reported(
+            test_file!("out_of_range_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            Mode::Sequential,
+            options,
+        )
+        .unwrap();
+        assert_eq!(report.output, temp_dir().join("presquile_reported.mp3"));
+        assert_eq!(report.chapter_count, 2);
+        assert_eq!(report.total_duration_ms, tag_end(&report.output));
+        assert_eq!(report.warnings.len(), 2);
+        assert!(matches!(
+            &report.warnings[0],
+            Warning::DroppedPastEnd { title, start: 300_000, .. } if title == "Outro"
+        ));
+        assert!(matches!(
+            &report.warnings[1],
+            Warning::ClampedToDuration { title, end_time: 150_636, .. } if title == "Advertising"
+        ));
+        assert!(report.warnings[0]
+            .to_string()
+            .starts_with("Dropping chapter \"Outro\": starts at 00:05:00.000"));
+    }
+
+    #[test]
+    fn test_duration_overflow() {
+        let long_dir = temp_dir().join("presquile_duration_overflow");
+        let _ = remove_dir_all(&long_dir);
+        create_dir_all(&long_dir).unwrap();
+        let long = long_dir.join("long_chaps.cvs");
+        write(
+            &long,
+            "Name\tStart\tDuration\tTime Format\tType\tDescription\n\
+             Intro\t0:00.000\t0:00.000\tdecimal\tCue\t\n\
+             Advertising\t1:00.000\t1193:02:47.000\tdecimal\tCue\t\n",
+        )
+        .unwrap();
+        let options = ApplyOptions::default();
+        let records = load_cvs(&long, &options).unwrap();
+        let mut warnings = Vec::new();
+        let chapters = process_chapters(records, 120_000f64, &options, &mut warnings).unwrap();
+        assert_eq!(chapters[1].end_time, 120_000);
+        assert!(matches!(
+            &warnings[..],
+            [Warning::ClampedToDuration {
+                end_time: u32::MAX,
+                ..
+            }]
+        ));
+    }
+
+    fn tag_end(mp3_file: &Path) -> u32 {
+        let tag = Tag::read_from_path(mp3_file).unwrap();
+        tag.chapters()
+            .map(|chapter| chapter.end_time)
+            .max()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_lenient_rows() {
+        let options = ApplyOptions {
+            lenient: true,
+            ..Default::default()
+        };
+        let mut warnings = Vec::new();
+        let records = load_cvs_reporting(
+            test_file!("mixed_rows_chaps.cvs").as_ref(),
+            &options,
+            &mut warnings,
+        )
+        .unwrap();
+        let names: Vec<_> = records.iter().map(|record| record.name.as_str()).collect();
+        assert_eq!(names, ["Intro", "Advertising"]);
+        assert_eq!(
+            warnings,
+            [
+                Warning::SkippedBadRow {
+                    line: 3,
+                    row: "Broken\tnot a time\t0:00.000\tdecimal\tCue\t".to_string()
+                },
+                Warning::SkippedBadRow {
+                    line: 5,
+                    row: "Outro\t1:20.000".to_string()
+                }
+            ]
+        );
+        assert_eq!(
+            warnings[1].to_string(),
+            "Skipping row 5: \"Outro\\t1:20.000\""
+        );
+
+        assert!(load_cvs(
+            test_file!("mixed_rows_chaps.cvs").as_ref(),
+            &ApplyOptions::default()
+        )
+        .is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::InvalidRow(3, line)) => line.starts_with("Broken\t"),
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn test_lenient_without_valid_rows() {
+        let options = ApplyOptions {
+            lenient: true,
+            ..Default::default()
+        };
+        let mut warnings = Vec::new();
+        let content = b"Name\tStart\nBroken\tnot a time\nOutro\n";
+        let records = read_cvs(&content[..], &options, &mut warnings);
+        assert!(records.is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::ChaptersFileNotCompatible) => true,
+            _ => false,
+        }));
+        assert_eq!(warnings.len(), 2);
+    }
+
+    #[test]
+    fn test_export_ffmetadata() {
+        let out = temp_dir().join("presquile_chapters.ffmetadata");
+        let exported = export_ffmetadata(
+            test_file!("valid_chaps.cvs").into(),
+            120_000f64,
+            out.clone(),
+        );
+        assert!(exported.is_ok());
+        assert_eq!(
+            read_to_string(out).unwrap(),
+            read_to_string(test_file!("valid_chaps.ffmetadata")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_export_webvtt() {
+        let out = temp_dir().join("presquile_chapters.vtt");
+        let exported = export_webvtt(
+            test_file!("valid_chaps.cvs").into(),
+            120_000f64,
+            out.clone(),
+        );
+        assert!(exported.is_ok());
+        let vtt = read_to_string(out).unwrap();
+        assert_eq!(vtt, read_to_string(test_file!("valid_chaps.vtt")).unwrap());
+
+        //Every timing line parses back to the chapters it came from
+        let timings: Vec<_> = vtt
+            .lines()
+            .filter_map(|line| line.split_once(" --> "))
+            .map(|(start, end)| (convert_time(start).unwrap(), convert_time(end).unwrap()))
+            .collect();
+        let chapters: Vec<_> = parse_chapters(test_file!("valid_chaps.cvs").into(), 120_000f64)
+            .unwrap()
+            .into_iter()
+            .map(|chap| (chap.start_time, chap.end_time))
+            .collect();
+        assert_eq!(timings, chapters);
+    }
+
+    #[test]
+    fn test_export_podcast_json() {
+        let out = temp_dir().join("presquile_chapters.json");
+        let exported = export_podcast_json(
+            test_file!("podcast_chaps.cvs").into(),
+            120_000f64,
+            out.clone(),
+        );
+        assert!(exported.is_ok());
+
+        let json: serde_json::Value = serde_json::from_str(&read_to_string(out).unwrap()).unwrap();
+        let expected: serde_json::Value =
+            serde_json::from_str(&read_to_string(test_file!("podcast_chaps.json")).unwrap())
+                .unwrap();
+        assert_eq!(json, expected);
+    }
+
+    #[test]
+    fn test_export_podcast_json_ids() {
+        let out = temp_dir().join("presquile_ids_chapters.json");
+        let exported = export_podcast_json(
+            test_file!("podcast_ids_chaps.cvs").into(),
+            120_000f64,
+            out.clone(),
+        );
+        assert!(exported.is_ok());
+
+        let json: serde_json::Value = serde_json::from_str(&read_to_string(out).unwrap()).unwrap();
+        let expected: serde_json::Value =
+            serde_json::from_str(&read_to_string(test_file!("podcast_chaps.json")).unwrap())
+                .unwrap();
+        assert_eq!(json, expected);
+    }
+
+    #[test]
+    fn test_audacity_labels() {
+        let chapters: Vec<_> = parse_chapters(test_file!("audacity_labels.txt").into(), 120_000f64)
+            .unwrap()
+            .into_iter()
+            .map(|chap| {
+                let title = chap.frames[0].content().text().unwrap().to_string();
+                (chap.start_time, chap.end_time, title)
+            })
+            .collect();
+        assert_eq!(
+            chapters,
+            [
+                (0, 60_636, "Intro".to_string()),
+                (60_636, 75_500, "Advertising".to_string()),
+                (75_500, 89_250, "Interview with a guest".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_youtube() {
+        let records = load_youtube(test_file!("youtube_chapters.txt").into()).unwrap();
+        let records: Vec<_> = records
+            .iter()
+            .map(|record| (record.start.as_str(), record.name.as_str()))
+            .collect();
+        assert_eq!(
+            records,
+            [
+                ("0:00", "Intro"),
+                ("01:00", "Advertising - our sponsor"),
+                ("1:02:03", "Q&A")
+            ]
+        );
+
+        assert!(load_youtube(test_file!("file.txt").into()).is_err_and(|e| {
+            match e.downcast_ref() {
+                Some(AppliersErrors::ChaptersFileNotCompatible) => true,
+                _ => false,
+            }
+        }));
+    }
+
+    #[test]
+    fn test_chapter_description() {
+        let output = temp_dir().join("presquile_description.mp3");
+        let new_mp3_file = apply_with_output(
+            test_file!("description_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            output.clone(),
+            Mode::Sequential,
+        );
+        assert!(new_mp3_file.is_ok());
+
+        let tag = Tag::read_from_path(&output).unwrap();
+        let descriptions: Vec<_> = tag
+            .chapters()
+            .map(|chap| {
+                chap.frames
+                    .iter()
+                    .find(|frame| frame.id() == "TIT3")
+                    .and_then(|frame| frame.content().text())
+            })
+            .collect();
+        assert_eq!(descriptions, [Some("Welcome to the show"), None]);
+    }
+
+    #[test]
+    fn test_chapter_url() {
+        let output = temp_dir().join("presquile_url.mp3");
+        let new_mp3_file = apply_with_output(
+            test_file!("podcast_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            output.clone(),
+            Mode::Sequential,
+        );
+        assert!(new_mp3_file.is_ok());
+
+        let tag = Tag::read_from_path(&output).unwrap();
+        let links: Vec<_> = tag
+            .chapters()
+            .map(|chap| {
+                chap.frames
+                    .iter()
+                    .find(|frame| frame.id() == "WXXX")
+                    .and_then(|frame| frame.content().extended_link())
+                    .map(|link| link.link.as_str())
+            })
+            .collect();
+        assert_eq!(
+            links,
+            [
+                None,
+                Some("https://example.com/guest"),
+                Some("https://example.com/sponsor")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chapter_image() {
+        let output = temp_dir().join("presquile_image.mp3");
+        let new_mp3_file = apply_with_output(
+            test_file!("image_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            output.clone(),
+            Mode::Sequential,
+        );
+        assert!(new_mp3_file.is_ok());
+
+        let tag = Tag::read_from_path(&output).unwrap();
+        let pictures: Vec<_> = tag
+            .chapters()
+            .map(|chap| {
+                chap.frames
+                    .iter()
+                    .find(|frame| frame.id() == "APIC")
+                    .and_then(|frame| frame.content().picture())
+                    .map(|picture| (picture.mime_type.as_str(), picture.data.clone()))
+            })
+            .collect();
+        assert_eq!(
+            pictures,
+            [
+                Some(("image/png", read(test_file!("cover.png")).unwrap())),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chapter_image_missing() {
+        assert!(apply_with_output(
+            test_file!("missing_image_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            temp_dir().join("presquile_missing_image.mp3"),
+            Mode::Sequential,
+        )
+        .is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::ChapterImage(name, _)) => name == "Advertising",
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn test_id3v23() {
+        let options = ApplyOptions {
+            output: Some(temp_dir().join("presquile_id3v23.mp3")),
+            id3_version: Some(Version::Id3v23),
+            ..Default::default()
+        };
+        let new_mp3_file = apply_with_options(
+            test_file!("valid_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            Mode::Sequential,
+            options,
+        );
+        assert!(new_mp3_file.is_ok());
+
+        let tag = Tag::read_from_path(new_mp3_file.unwrap()).unwrap();
+        assert_eq!(tag.version(), Version::Id3v23);
+        let chapters: Vec<_> = tag
+            .chapters()
+            .map(|chap| {
+                (
+                    chap.element_id.as_str(),
+                    chap.start_time,
+                    chap.frames[0].content().text(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            chapters,
+            [
+                ("chp001", 0, Some("Intro")),
+                ("chp002", 60_636, Some("Advertising"))
+            ]
+        );
+        let toc = tag.tables_of_contents().next().unwrap();
+        assert_eq!(toc.elements, ["chp001", "chp002"]);
+    }
+
+    #[test]
+    fn test_id3v1() {
+        let output_dir = temp_dir().join("presquile_id3v1");
+        let _ = remove_dir_all(&output_dir);
+        create_dir_all(&output_dir).unwrap();
+        let source = output_dir.join("source.mp3");
+        copy(test_file!("audio.mp3"), &source).unwrap();
+        let mut tag = Tag::read_from_path(&source).unwrap();
+        tag.set_artist("Presquile Radio");
+        tag.set_track(7);
+        tag.write_to_path(&source, Version::Id3v24).unwrap();
+
+        let apply = |options: ApplyOptions| {
+            apply_with_options(
+                test_file!("valid_chaps.cvs").into(),
+                source.clone(),
+                Mode::Sequential,
+                options.with_toc_title("Épisode 12: a title too long for ID3v1"),
+            )
+            .unwrap()
+        };
+        let plain = apply(ApplyOptions::default().with_output(output_dir.join("plain.mp3")));
+        assert!(id3::v1::Tag::read_from_path(plain).is_err());
+
+        let options = ApplyOptions::default()
+            .with_output(output_dir.join("v1.mp3"))
+            .with_id3v1(true);
+        let new_mp3_file = apply(options.clone());
+        let v1 = id3::v1::Tag::read_from_path(&new_mp3_file).unwrap();
+        assert_eq!(v1.title, "Épisode 12: a title too long f");
+        assert_eq!(v1.artist, "Presquile Radio");
+        assert_eq!(v1.track, Some(7));
+        let tag = Tag::read_from_path(&new_mp3_file).unwrap();
+        assert_eq!(tag.chapters().count(), 2);
+        assert_eq!(tag.artist(), Some("Presquile Radio"));
+
+        //Applying again replaces the ID3v1 tag rather than stacking a second one
+        let size = metadata(&new_mp3_file).unwrap().len();
+        copy(&new_mp3_file, &source).unwrap();
+        let again = apply(options.with_output(output_dir.join("again.mp3")));
+        assert_eq!(metadata(again).unwrap().len(), size);
+    }
+
+    #[test]
+    fn test_no_toc() {
+        let no_toc_dir = temp_dir().join("presquile_no_toc");
+        let _ = remove_dir_all(&no_toc_dir);
+        for cvs in [
+            test_file!("valid_chaps.cvs"),
+            test_file!("nested_chaps.cvs"),
+        ] {
+            let new_mp3_file = apply_with_options(
+                cvs.into(),
+                test_file!("audio.mp3").into(),
+                Mode::Sequential,
+                ApplyOptions::default()
+                    .with_output(no_toc_dir.join("episode.mp3"))
+                    .with_toc(false),
+            )
+            .unwrap();
+
+            let tag = Tag::read_from_path(new_mp3_file).unwrap();
+            assert!(tag.chapters().count() > 0);
+            assert_eq!(tag.tables_of_contents().count(), 0);
+        }
+    }
+
+    #[test]
+    fn test_toc_title() {
+        let toc = |options: ApplyOptions, source: PathBuf| {
+            let new_mp3_file = apply_with_options(
+                test_file!("valid_chaps.cvs").into(),
+                source,
+                Mode::Sequential,
+                options,
+            )
+            .unwrap();
+            let tag = Tag::read_from_path(new_mp3_file).unwrap();
+            let toc = tag.tables_of_contents().next().unwrap().clone();
+            let title = toc.frames[0].content().text().unwrap().to_string();
+            (toc.element_id, title)
+        };
+        let output_dir = temp_dir().join("presquile_toc_title");
+        let _ = remove_dir_all(&output_dir);
+        create_dir_all(&output_dir).unwrap();
+
+        let options = ApplyOptions {
+            output: Some(output_dir.join("default.mp3")),
+            ..Default::default()
+        };
+        assert_eq!(
+            toc(options, test_file!("audio.mp3").into()),
+            ("toc".to_string(), "Chapters".to_string())
+        );
+
+        let options = ApplyOptions {
+            output: Some(output_dir.join("custom.mp3")),
+            toc_title: Some("Episode 1".to_string()),
+            toc_id: Some("chapters".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            toc(options, test_file!("audio.mp3").into()),
+            ("chapters".to_string(), "Episode 1".to_string())
+        );
+
+        let source = output_dir.join("album.mp3");
+        copy(test_file!("audio.mp3"), &source).unwrap();
+        let mut tag = Tag::read_from_path(&source).unwrap();
+        tag.set_album("Season 2");
+        tag.write_to_path(&source, Version::Id3v24).unwrap();
+        assert_eq!(
+            toc(ApplyOptions::default(), source),
+            ("toc".to_string(), "Season 2".to_string())
+        );
+    }
+
+    #[test]
+    fn test_toc_flags() {
+        let options = ApplyOptions {
+            output: Some(temp_dir().join("presquile_toc_flags.mp3")),
+            toc_top_level: Some(false),
+            toc_ordered: Some(false),
+            ..Default::default()
+        };
+        let new_mp3_file = apply_with_options(
+            test_file!("valid_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            Mode::Sequential,
+            options,
+        );
+        let tag = Tag::read_from_path(new_mp3_file.unwrap()).unwrap();
+        let toc = tag.tables_of_contents().next().unwrap();
+        assert!(!toc.top_level && !toc.ordered);
+        assert_eq!(toc.frames.len(), 1);
+        assert_eq!(toc.elements, ["chp001", "chp002"]);
+    }
+
+    #[test]
+    fn test_keep_existing_output() {
+        let output_dir = temp_dir().join("presquile_keep_existing");
+        let _ = remove_dir_all(&output_dir);
+        create_dir_all(&output_dir).unwrap();
+        let output = output_dir.join("episode.mp3");
+        write(&output, "hand edited").unwrap();
+
+        let options = ApplyOptions {
+            output: Some(output.clone()),
+            keep_existing: true,
+            ..Default::default()
+        };
+        let refused = apply_with_options(
+            test_file!("valid_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            Mode::Sequential,
+            options,
+        );
+        assert!(refused.is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::OutputExists(path)) => *path == output.display().to_string(),
+            _ => false,
+        }));
+        assert_eq!(read_to_string(&output).unwrap(), "hand edited");
+
+        let forced = apply_with_output(
+            test_file!("valid_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            output.clone(),
+            Mode::Sequential,
+        );
+        assert!(forced.is_ok());
+        let tag = Tag::read_from_path(&output);
+        assert!(tag.is_ok_and(|tag| tag.chapters().count() == 2));
+    }
+
+    #[test]
+    fn test_keep_chapters() {
+        let enriched = temp_dir().join("presquile_keep_chapters.mp3");
+        apply_with_output(
+            test_file!("valid_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            enriched.clone(),
+            Mode::Sequential,
+        )
+        .unwrap();
+        let again = |options: ApplyOptions| {
+            apply_with_options(
+                test_file!("valid_chaps.cvs").into(),
+                enriched.clone(),
+                Mode::Sequential,
+                options.with_output(temp_dir().join("presquile_keep_chapters_again.mp3")),
+            )
+        };
+
+        let refused = again(ApplyOptions::default().with_keep_chapters(true));
+        assert!(refused.is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::HasChapters(path)) => *path == enriched.display().to_string(),
+            _ => false,
+        }));
+        let merged = again(
+            ApplyOptions::default()
+                .with_keep_chapters(true)
+                .with_merge(true),
+        );
+        assert!(merged.is_ok());
+        assert!(again(ApplyOptions::default()).is_ok());
+    }
+
+    #[test]
+    fn test_chapter_id() {
+        assert_eq!(chapter_id("chp", 0, 2), "chp001");
+        assert_eq!(chapter_id("chp", 41, 999), "chp042");
+        assert_eq!(chapter_id("part-", 41, 1200), "part-0042");
+    }
+
+    #[test]
+    fn test_merge_chapters() {
+        let old_count = Tag::read_from_path(test_file!("audio.mp3"))
+            .unwrap()
+            .chapters()
+            .count();
+        let options = ApplyOptions {
+            output: Some(temp_dir().join("presquile_merge.mp3")),
+            merge: true,
+            ..Default::default()
+        };
+        let report = apply_reported(
+            test_file!("unsorted_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            Mode::Sequential,
+            options,
+        )
+        .unwrap();
+
+        let tag = Tag::read_from_path(&report.output).unwrap();
+        let chapters: Vec<_> = tag.chapters().collect();
+        assert_eq!(chapters.len(), old_count + report.chapter_count);
+        assert!(chapters
+            .windows(2)
+            .all(|pair| pair[0].start_time <= pair[1].start_time));
+
+        let ids: Vec<_> = chapters
+            .iter()
+            .map(|chap| chap.element_id.as_str())
+            .collect();
+        assert_eq!(ids, ["chp001", "chp002", "chp003", "chp004", "chp005"]);
+        let tocs: Vec<_> = tag.tables_of_contents().collect();
+        assert_eq!(tocs.len(), 1);
+        assert_eq!(tocs[0].elements, ids);
+    }
+
+    #[test]
+    fn test_merge_chapters_ids() {
+        let old_count = Tag::read_from_path(test_file!("audio.mp3"))
+            .unwrap()
+            .chapters()
+            .count();
+        let merge_dir = temp_dir().join("presquile_merge_ids");
+        let _ = remove_dir_all(&merge_dir);
+        let options = ApplyOptions::default()
+            .with_output(merge_dir.join("episode.mp3"))
+            .with_merge(true);
+        let report = apply_reported(
+            test_file!("ids_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            Mode::Sequential,
+            options,
+        )
+        .unwrap();
+
+        //Only the numbered ids follow the position of their chapter
+        let tag = Tag::read_from_path(&report.output).unwrap();
+        let ids: Vec<_> = tag.chapters().map(|chap| chap.element_id.clone()).collect();
+        assert_eq!(ids.len(), old_count + report.chapter_count);
+        assert!(ids.contains(&"intro".to_string()));
+        assert!(ids.contains(&"interview-main".to_string()));
+        let numbered = ids.iter().filter(|id| id.starts_with("chp")).count();
+        assert_eq!(numbered, ids.len() - 2);
+        assert_eq!(
+            tag.tables_of_contents().next().unwrap().elements.len(),
+            ids.len()
+        );
     }
-}
 
-#[derive(new)]
-struct Applier {
-    audition_cvs: PathBuf,
-    mp3_file: PathBuf,
-}
+    #[test]
+    fn test_headerless_columns() {
+        let expected = load_cvs(
+            test_file!("valid_chaps.cvs").as_ref(),
+            &ApplyOptions::default(),
+        )
+        .unwrap();
+        let options = ApplyOptions {
+            columns: Some(vec![
+                "start".into(),
+                "name".into(),
+                "duration".into(),
+                "type".into(),
+            ]),
+            ..Default::default()
+        };
+        let records = load_cvs(test_file!("headerless_chaps.cvs").as_ref(), &options);
+        assert!(records.is_ok_and(|records| records == expected));
 
-impl Applier {
-    fn apply_seq(&self) -> Result<PathBuf> {
-        let cvs = self.load_cvs()?;
-        let duration = self.verify_mp3_file()?;
-        let tag = Self::build_tag(cvs, duration);
-        let new_mp3_file = self.copy_file()?;
-        tag.write_to_path(new_mp3_file.clone(), Version::Id3v24)?;
-        Ok(new_mp3_file)
+        let records = load_cvs(
+            test_file!("headerless_chaps.cvs").as_ref(),
+            &ApplyOptions::default(),
+        );
+        assert!(records.is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::ChaptersFileNotCompatible) => true,
+            _ => false,
+        }));
     }
 
-    fn apply_parallel(&self) -> Result<PathBuf> {
-        use crate::AppliersErrors::ThreadInterrupted;
+    #[test]
+    fn test_headerless_missing_start() {
+        let options = ApplyOptions {
+            columns: Some(vec!["duration".into(), "name".into()]),
+            ..Default::default()
+        };
+        let records = load_cvs(test_file!("headerless_chaps.cvs").as_ref(), &options);
+        assert!(records.is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::ChaptersFileNotCompatible) => true,
+            _ => false,
+        }));
+    }
 
-        let (tag, new_mp3_file) = thread::scope(|s| {
-            let cvs = s.spawn(|| self.load_cvs());
-            let duration = s.spawn(|| self.verify_mp3_file());
-            let new_mp3_file = s.spawn(|| self.copy_file());
+    #[test]
+    fn test_utf8_bom() {
+        let expected = load_cvs(
+            test_file!("valid_chaps.cvs").as_ref(),
+            &ApplyOptions::default(),
+        )
+        .unwrap();
+        let records = load_cvs(
+            test_file!("valid_chaps_utf8_bom.cvs").as_ref(),
+            &ApplyOptions::default(),
+        );
+        assert!(records.is_ok_and(|records| records == expected));
+    }
 
-            let cvs = cvs.join().map_err(|_| ThreadInterrupted)??;
-            let duration = duration.join().map_err(|_| ThreadInterrupted)??;
+    #[test]
+    fn test_utf16() {
+        let expected = load_cvs(
+            test_file!("valid_chaps.cvs").as_ref(),
+            &ApplyOptions::default(),
+        )
+        .unwrap();
+        let records = load_cvs(
+            test_file!("valid_chaps_utf16.cvs").as_ref(),
+            &ApplyOptions::default(),
+        );
+        assert!(records.is_ok_and(|records| records == expected));
+    }
 
-            let tag = Self::build_tag(cvs, duration);
-            let new_mp3_file = new_mp3_file.join().map_err(|_| ThreadInterrupted)??;
-            anyhow::Ok((tag, new_mp3_file))
-        })?;
+    #[test]
+    fn test_batch_progress() {
+        let batch_dir = temp_dir().join("presquile_batch_progress");
+        let _ = remove_dir_all(&batch_dir);
+        create_dir_all(&batch_dir).unwrap();
+        copy(test_file!("audio.mp3"), batch_dir.join("first.mp3")).unwrap();
+        copy(test_file!("valid_chaps.cvs"), batch_dir.join("first.cvs")).unwrap();
+        copy(test_file!("audio.mp3"), batch_dir.join("second.mp3")).unwrap();
+        copy(test_file!("valid_chaps.cvs"), batch_dir.join("second.cvs")).unwrap();
+        copy(test_file!("valid_chaps.cvs"), batch_dir.join("orphan.cvs")).unwrap();
 
-        tag.write_to_path(&new_mp3_file, Version::Id3v24)?;
-        Ok(new_mp3_file)
+        for mode in [Mode::Sequential, Mode::Parallel] {
+            let mut seen = Vec::new();
+            let results = batch_with_progress(batch_dir.clone(), mode, |file, _| {
+                seen.push(file.to_path_buf())
+            })
+            .unwrap();
+            seen.sort();
+            let files: Vec<_> = results.into_iter().map(|(file, _)| file).collect();
+            assert_eq!(seen, files);
+            assert_eq!(seen.len(), 3);
+        }
     }
 
-    fn copy_file(&self) -> Result<PathBuf> {
-        let file_name = self.mp3_file.file_stem().and_then(|file| file.to_str());
-        ensure!(file_name.is_some(), AppliersErrors::CopyFile);
+    #[test]
+    fn test_single_marker() {
+        let chapters = parse_chapters(test_file!("single_chaps.cvs").into(), 120_000f64).unwrap();
+        assert_eq!(chapters.len(), 1);
+        assert_eq!(
+            (chapters[0].start_time, chapters[0].end_time),
+            (5_000, 120_000)
+        );
 
-        let new_mp3_file = self
-            .mp3_file
-            .with_file_name(file_name.unwrap().to_owned() + "_enriched.mp3");
-        copy(&self.mp3_file, &new_mp3_file)?;
+        let single_dir = temp_dir().join("presquile_single_marker");
+        let _ = remove_dir_all(&single_dir);
+        for mode in [Mode::Sequential, Mode::Parallel] {
+            let new_mp3_file = apply_with_output(
+                test_file!("single_chaps.cvs").into(),
+                test_file!("audio.mp3").into(),
+                single_dir.join("episode.mp3"),
+                mode,
+            )
+            .unwrap();
 
-        Ok(new_mp3_file)
+            let metadata = MediaFileMetadata::new(&test_file!("audio.mp3")).unwrap();
+            let duration = (metadata._duration.unwrap() * 1000f64) as u32;
+            let tag = Tag::read_from_path(new_mp3_file).unwrap();
+            let chapters: Vec<_> = tag.chapters().collect();
+            assert_eq!(chapters.len(), 1);
+            assert_eq!(
+                (chapters[0].start_time, chapters[0].end_time),
+                (5_000, duration)
+            );
+            let toc = tag.tables_of_contents().next().unwrap();
+            assert_eq!(toc.elements, [chapters[0].element_id.clone()]);
+        }
     }
 
-    fn convert_time(time: &str) -> u32 {
-        //Precalculate 100*(pow(60,n)) to avoid inconsistency between bench runs
-        let multipliers = [1000, 60 * 1000, 60 * 60 * 1000u32];
+    #[test]
+    fn test_mp3_frames_duration() {
+        let metadata = MediaFileMetadata::new(&test_file!("audio.mp3")).unwrap();
+        let expected = metadata._duration.unwrap();
+        let probe = |file| probe::mp3_duration(&mut File::open(file).unwrap()).unwrap();
+        let duration = probe(test_file!("audio.mp3")).unwrap();
+        assert!((duration - expected).abs() < 0.1);
+        //Rebuilt from the frames of a constant bitrate file too, without a VBR header
+        assert!(probe(test_file!("cbr.mp3")).is_some_and(|duration| duration > 0f64));
 
-        let (hh_mm_ss, milliseconds) = time.split_once('.').unwrap();
-        let hh_mm_ss = hh_mm_ss.split(':');
+        assert!(probe(test_file!("audio.ogg")).is_none());
+        assert!(probe(test_file!("file.txt")).is_none());
+    }
 
-        hh_mm_ss
-            .map(|v| v.parse::<u32>().unwrap())
-            .rev()
-            .enumerate()
-            .map(|(idx, val)| val * multipliers[idx])
-            .chain(iter::once(milliseconds.parse().unwrap()))
-            .sum()
+    #[test]
+    fn test_unknown_duration() {
+        let result = apply(
+            test_file!("valid_chaps.cvs").into(),
+            test_file!("no_audio.mp3").into(),
+            Mode::Sequential,
+        );
+        assert!(result.is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::DurationUnknown(_)) => true,
+            _ => false,
+        }));
     }
 
-    fn build_tag(cvs: AuditionCvsRecords, duration: f64) -> Tag {
-        let mut tag = Tag::new();
-        let chapter_ids: Vec<_> = Self::build_chapters(cvs, duration)
-            .map(|chapter| {
-                let id = chapter.element_id.clone();
-                tag.add_frame(chapter);
-                id
-            })
-            .collect();
-        tag.add_frame(TableOfContents {
-            element_id: "toc".to_string(),
-            top_level: true,
-            ordered: true,
-            elements: chapter_ids,
-            frames: vec![Frame::text("TIT2", "chapters-chapz"); 1],
-        });
+    #[test]
+    fn test_batch_parallel() {
+        let batch_dir = temp_dir().join("presquile_batch_pool");
+        let _ = remove_dir_all(&batch_dir);
+        create_dir_all(&batch_dir).unwrap();
+        for name in ["first", "second", "third"] {
+            copy(
+                test_file!("audio.mp3"),
+                batch_dir.join(format!("{name}.mp3")),
+            )
+            .unwrap();
+            copy(
+                test_file!("valid_chaps.cvs"),
+                batch_dir.join(format!("{name}.cvs")),
+            )
+            .unwrap();
+        }
+        copy(test_file!("valid_chaps.cvs"), batch_dir.join("orphan.cvs")).unwrap();
 
-        tag
+        let expected = batch(batch_dir.clone(), Mode::Sequential).unwrap();
+        let results = batch_parallel(batch_dir.clone(), 2).unwrap();
+        assert_eq!(results.len(), expected.len());
+        for ((file, output), (expected_file, expected_output)) in results.iter().zip(&expected) {
+            assert_eq!(file, expected_file);
+            assert_eq!(output.as_ref().ok(), expected_output.as_ref().ok());
+        }
     }
 
-    fn build_chapters(records: AuditionCvsRecords, duration: f64) -> impl Iterator<Item = Chapter> {
-        let mut end_time = duration as u32;
-        records
-            .into_iter()
-            .enumerate()
-            .rev()
-            .map(move |(id, record)| {
-                let start_time = Self::convert_time(&record.start);
-                let ch = Chapter {
-                    element_id: id.to_string(),
-                    start_time,
-                    end_time,
-                    start_offset: 0,
-                    end_offset: 0,
-                    frames: vec![Frame::text("TIT2", record.name); 1],
-                };
-                end_time = start_time;
-                ch
-            })
-            .rev()
+    #[test]
+    fn test_options_builder() {
+        let options = ApplyOptions::default()
+            .with_output("out.mp3")
+            .with_suffix("_chapters")
+            .with_id3_version(Version::Id3v23)
+            .with_toc_title("Contents")
+            .with_min_chapter_duration(1_000)
+            .with_lenient(true);
+        assert_eq!(options.output, Some(PathBuf::from("out.mp3")));
+        assert_eq!(options.suffix.as_deref(), Some("_chapters"));
+        assert_eq!(options.id3_version, Some(Version::Id3v23));
+        assert_eq!(options.toc_title.as_deref(), Some("Contents"));
+        assert_eq!(options.min_chapter_duration, 1_000);
+        assert!(options.lenient);
+        assert!(!options.merge && options.toc_id.is_none());
     }
 
-    fn load_cvs(&self) -> Result<AuditionCvsRecords> {
-        let mut rdr = ReaderBuilder::new()
-            .delimiter(b'\t')
-            .trim(csv::Trim::All)
-            .from_path(self.audition_cvs.as_path())?;
-
-        let (error, data): (Vec<_>, Vec<_>) = rdr.deserialize().partition(|line| line.is_err());
-        let data: AuditionCvsRecords = data.into_iter().map(|f| f.unwrap()).collect();
-        ensure!(
-            error.is_empty() && !data.is_empty(),
-            AppliersErrors::ChaptersFileNotCompatible
+    #[test]
+    fn test_marker_type_filter() {
+        let records = load_cvs(
+            test_file!("mixed_types_chaps.cvs").as_ref(),
+            &ApplyOptions::default(),
         );
+        assert!(records.is_ok_and(|records| records.len() == 4));
 
-        for record in data.iter() {
-            ensure!(
-                record.start.contains(':') && record.start.contains('.'),
-                AppliersErrors::ChaptersFileNotCompatible
-            );
-        }
-        Ok(data)
-    }
+        let options = ApplyOptions::default().with_marker_type("Cue");
+        let records = load_cvs(test_file!("mixed_types_chaps.cvs").as_ref(), &options).unwrap();
+        let names: Vec<_> = records.iter().map(|record| record.name.as_str()).collect();
+        assert_eq!(names, ["Intro", "Advertising"]);
 
-    fn verify_mp3_file(&self) -> Result<f64> {
-        match MediaFileMetadata::new(&self.mp3_file) {
-            Ok(metadata) => match metadata.container_format.as_str() {
-                "MP3" => Ok(metadata._duration.unwrap() * 1000f64),
-                _ => bail!(AppliersErrors::AudioFileNotCompatible(
-                    metadata.container_format
-                )),
-            },
-            Err(_) => bail!(AppliersErrors::AudioFileNotCompatible(
-                self.mp3_file.display().to_string()
-            )),
-        }
+        let options = ApplyOptions::default().with_marker_type("Marker");
+        let records = load_cvs(test_file!("mixed_types_chaps.cvs").as_ref(), &options);
+        assert!(records.is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::ChaptersFileNotCompatible) => true,
+            _ => false,
+        }));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use id3::Tag;
+    #[test]
+    fn test_comments_and_blank_lines() {
+        let expected = load_cvs(
+            test_file!("valid_chaps.cvs").as_ref(),
+            &ApplyOptions::default(),
+        )
+        .unwrap();
+        let records = load_cvs(
+            test_file!("commented_chaps.cvs").as_ref(),
+            &ApplyOptions::default(),
+        );
+        assert!(records.is_ok_and(|records| records == expected));
+    }
 
-    use crate::{apply, AppliersErrors, Mode};
+    #[test]
+    fn test_untitled_chapters() {
+        let output = temp_dir().join("presquile_untitled.mp3");
+        let options = ApplyOptions::default().with_output(&output);
+        apply_with_options(
+            test_file!("untitled_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            Mode::Sequential,
+            options,
+        )
+        .unwrap();
+        let tag = Tag::read_from_path(&output).unwrap();
+        let titles: Vec<_> = tag.chapters().map(chapter_title).collect();
+        assert_eq!(titles, ["Intro", "Chapter 2", "Chapter 3"]);
 
-    macro_rules! test_file {
-        ($file_name:expr) => {
-            concat!(env!("CARGO_MANIFEST_DIR"), "/resources/test/", $file_name)
-        };
+        let options = ApplyOptions::default()
+            .with_output(&output)
+            .with_untitled_prefix("Part");
+        apply_with_options(
+            test_file!("untitled_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            Mode::Sequential,
+            options,
+        )
+        .unwrap();
+        let tag = Tag::read_from_path(&output).unwrap();
+        let titles: Vec<_> = tag.chapters().map(chapter_title).collect();
+        assert_eq!(titles, ["Intro", "Part 2", "Part 3"]);
     }
 
     #[test]
-    fn test_not_audio_parallel() {
-        assert!(apply(
-            test_file!("valid_chaps.cvs").into(),
-            test_file!("file.txt").into(),
-            Mode::Parallel,
+    fn test_duplicate_markers() {
+        let mut warnings = Vec::new();
+        let records = load_cvs_reporting(
+            test_file!("duplicate_chaps.cvs").as_ref(),
+            &ApplyOptions::default(),
+            &mut warnings,
         )
-        .is_err_and(|e| match e.downcast_ref() {
-            Some(AppliersErrors::AudioFileNotCompatible(_)) => true,
-            _ => false,
-        }))
+        .unwrap();
+        let names: Vec<_> = records.iter().map(|record| record.name.as_str()).collect();
+        assert_eq!(names, ["Intro", "Advertising"]);
+        assert_eq!(
+            warnings,
+            [Warning::DroppedZeroLength {
+                title: "Advertising again".to_string(),
+                start: 60_636,
+                previous: "Advertising".to_string(),
+            }]
+        );
     }
 
     #[test]
-    fn test_not_mp3_audio_parallel() {
-        assert!(apply(
+    fn test_extract_round_trip() {
+        let extract_dir = temp_dir().join("presquile_extract");
+        let _ = remove_dir_all(&extract_dir);
+        create_dir_all(&extract_dir).unwrap();
+        let enriched = apply_with_output(
+            test_file!("description_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            extract_dir.join("first.mp3"),
+            Mode::Sequential,
+        )
+        .unwrap();
+
+        let extracted = extract_dir.join("extracted.cvs");
+        extract(enriched.clone(), extracted.clone()).unwrap();
+        let reapplied = apply_with_output(
+            extracted,
+            test_file!("audio.mp3").into(),
+            extract_dir.join("second.mp3"),
+            Mode::Sequential,
+        )
+        .unwrap();
+
+        let chapters = |path: &Path| -> Vec<Chapter> {
+            let tag = Tag::read_from_path(path).unwrap();
+            tag.chapters().cloned().collect()
+        };
+        let (expected, chapters) = (chapters(&enriched), chapters(&reapplied));
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters, expected);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_apply_async() {
+        let source_dir = temp_dir().join("presquile_apply_async");
+        let _ = remove_dir_all(&source_dir);
+        create_dir_all(&source_dir).unwrap();
+        let source = source_dir.join("audio.mp3");
+        copy(test_file!("audio.mp3"), &source).unwrap();
+
+        let new_mp3_file = crate::apply_async(
             test_file!("valid_chaps.cvs").into(),
-            test_file!("audio.ogg").into(),
-            Mode::Parallel,
+            source,
+            Mode::Sequential,
         )
-        .is_err_and(|e| match e.downcast_ref() {
-            Some(AppliersErrors::AudioFileNotCompatible(_)) => true,
-            _ => false,
-        }))
+        .await;
+        assert!(new_mp3_file.is_ok_and(|path| path.exists()));
     }
 
     #[test]
-    fn test_not_cvs_parallel() {
-        assert!(apply(
-            test_file!("file.txt").into(),
+    fn test_copy_progress() {
+        let copied = Mutex::new(Vec::new());
+        let options =
+            ApplyOptions::default().with_output(temp_dir().join("presquile_progress.mp3"));
+        let report = apply_with_progress(
+            test_file!("valid_chaps.cvs").into(),
             test_file!("audio.mp3").into(),
             Mode::Parallel,
+            options,
+            |bytes, total| copied.lock().unwrap().push((bytes, total)),
         )
-        .is_err_and(|e| match e.downcast_ref() {
-            Some(AppliersErrors::ChaptersFileNotCompatible) => true,
-            _ => false,
-        }))
+        .unwrap();
+        assert!(report.output.exists());
+
+        let size = read(test_file!("audio.mp3")).unwrap().len() as u64;
+        let copied = copied.into_inner().unwrap();
+        assert!(copied.len() > 2);
+        assert!(copied.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(copied.last(), Some(&(size, size)));
     }
 
     #[test]
-    fn test_invalid_cvs_parallel() {
-        assert!(apply(
-            test_file!("invalid_chaps.cvs").into(),
-            test_file!("audio.mp3").into(),
-            Mode::Parallel,
+    fn test_wav_cue_points() {
+        let new_wav_file = apply_with_output(
+            test_file!("wav_chaps.cvs").into(),
+            test_file!("audio.wav").into(),
+            temp_dir().join("presquile_cues.wav"),
+            Mode::Sequential,
         )
-        .is_err_and(|e| match e.downcast_ref() {
-            Some(AppliersErrors::ChaptersFileNotCompatible) => true,
-            _ => false,
-        }))
+        .unwrap();
+        let cues = writer::cue_points(&read(&new_wav_file).unwrap());
+        assert_eq!(
+            cues,
+            [(0, "Intro".to_string()), (20_000, "First take".to_string())]
+        );
+
+        //Applying again replaces the cue points instead of piling them up
+        let new_wav_file = apply_with_output(
+            test_file!("wav_chaps.cvs").into(),
+            new_wav_file.clone(),
+            temp_dir().join("presquile_cues_again.wav"),
+            Mode::Sequential,
+        )
+        .unwrap();
+        assert_eq!(writer::cue_points(&read(new_wav_file).unwrap()).len(), 2);
     }
 
     #[test]
-    fn test_best_case_parallel() {
-        let new_mp3_file = apply(
-            test_file!("valid_chaps.cvs").into(),
-            test_file!("audio.mp3").into(),
-            Mode::Parallel,
+    fn test_short_last_chapter() {
+        let records = || {
+            load_cvs(
+                test_file!("late_last_chaps.cvs").as_ref(),
+                &ApplyOptions::default(),
+            )
+        };
+        let mut warnings = Vec::new();
+        let chapters = process_chapters(
+            records().unwrap(),
+            120_000f64,
+            &ApplyOptions::default(),
+            &mut warnings,
+        );
+        assert!(chapters.is_ok_and(|chapters| chapters.len() == 2));
+        assert_eq!(
+            warnings,
+            [Warning::ShortLastChapter {
+                title: "Credits".to_string(),
+                length: 500,
+                ratio: 0.01,
+            }]
+        );
+        assert_eq!(
+            warnings[0].to_string(),
+            "The last chapter \"Credits\" lasts 00:00:00.500, less than 1% of the audio"
         );
-        assert!(new_mp3_file.is_ok());
 
-        let tag = Tag::read_from_path(new_mp3_file.unwrap());
-        assert!(tag.is_ok());
-        let tag = tag.unwrap();
+        let options = ApplyOptions::default().with_strict_span(true);
+        let chapters = process_chapters(records().unwrap(), 120_000f64, &options, &mut Vec::new());
+        assert!(chapters.is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::MarkersSpan(_)) => true,
+            _ => false,
+        }));
 
-        let chapters: Vec<_> = tag.chapters().collect();
-        assert!(!chapters.is_empty());
-        println!("{:?}", chapters);
+        let options = ApplyOptions::default().with_min_last_chapter_ratio(0.001);
+        let mut warnings = Vec::new();
+        process_chapters(records().unwrap(), 120_000f64, &options, &mut warnings).unwrap();
+        assert!(warnings.is_empty());
+    }
 
-        let ctocs: Vec<_> = tag.tables_of_contents().collect();
-        assert_eq!(ctocs.len(), 1);
-        println!("{:?}", ctocs);
+    #[test]
+    fn test_last_marker_past_the_end() {
+        let records = || {
+            load_cvs(
+                test_file!("out_of_range_chaps.cvs").as_ref(),
+                &ApplyOptions::default(),
+            )
+        };
+        let mut warnings = Vec::new();
+        let chapters = process_chapters(
+            records().unwrap(),
+            120_000f64,
+            &ApplyOptions::default(),
+            &mut warnings,
+        );
+        assert!(chapters.is_ok_and(|chapters| chapters.len() == 2));
+        assert_eq!(
+            warnings[0],
+            Warning::DroppedPastEnd {
+                title: "Outro".to_string(),
+                start: 300_000,
+                audio_end: 120_000,
+            }
+        );
 
-        chapters
-            .iter()
-            .zip(ctocs.last().unwrap().elements.iter())
-            .for_each(|(chap, chap_id)| assert_eq!(chap.element_id, *chap_id));
+        let options = ApplyOptions::default().with_strict_span(true);
+        let chapters = process_chapters(records().unwrap(), 120_000f64, &options, &mut Vec::new());
+        assert!(chapters.is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::MarkersSpan(message)) => message.contains("Outro"),
+            _ => false,
+        }));
     }
 
     #[test]
-    fn test_not_audio_seq() {
-        assert!(apply(
+    fn test_preserve_mtime() {
+        let mtime_dir = temp_dir().join("presquile_preserve_mtime");
+        let _ = remove_dir_all(&mtime_dir);
+        create_dir_all(&mtime_dir).unwrap();
+        let mp3_file = mtime_dir.join("episode.mp3");
+        copy(test_file!("audio.mp3"), &mp3_file).unwrap();
+        let source_mtime = FileTime::from_unix_time(1_600_000_000, 0);
+        set_file_mtime(&mp3_file, source_mtime).unwrap();
+
+        for mode in [Mode::Sequential, Mode::Parallel] {
+            let options = ApplyOptions::default().with_preserve_mtime(true);
+            let new_mp3_file = apply_with_options(
+                test_file!("valid_chaps.cvs").into(),
+                mp3_file.clone(),
+                mode,
+                options,
+            )
+            .unwrap();
+            let mtime = FileTime::from_last_modification_time(&metadata(&new_mp3_file).unwrap());
+            assert!((mtime.unix_seconds() - source_mtime.unix_seconds()).abs() <= 1);
+        }
+
+        let new_mp3_file = apply(
             test_file!("valid_chaps.cvs").into(),
-            test_file!("file.txt").into(),
+            mp3_file,
             Mode::Sequential,
         )
-        .is_err_and(|e| match e.downcast_ref() {
-            Some(AppliersErrors::AudioFileNotCompatible(_)) => true,
-            _ => false,
-        }))
+        .unwrap();
+        let mtime = FileTime::from_last_modification_time(&metadata(&new_mp3_file).unwrap());
+        assert!(mtime.unix_seconds() > source_mtime.unix_seconds());
     }
 
     #[test]
-    fn test_not_mp3_audio_seq() {
-        assert!(apply(
+    fn test_byte_offsets() {
+        let offsets_dir = temp_dir().join("presquile_byte_offsets");
+        let _ = remove_dir_all(&offsets_dir);
+        create_dir_all(&offsets_dir).unwrap();
+        let options = ApplyOptions::default().with_byte_offsets(true);
+
+        let cbr_file = offsets_dir.join("cbr.mp3");
+        copy(test_file!("cbr.mp3"), &cbr_file).unwrap();
+        let new_mp3_file = apply_with_options(
+            test_file!("wav_chaps.cvs").into(),
+            cbr_file.clone(),
+            Mode::Sequential,
+            options.clone(),
+        )
+        .unwrap();
+        let tag = Tag::read_from_path(&new_mp3_file).unwrap();
+        let mut chapters: Vec<_> = tag.chapters().collect();
+        chapters.sort_by_key(|chapter| chapter.start_time);
+        assert_eq!(chapters.len(), 2);
+        //Offsets count from the start of the file, the first chapter points at the first frame
+        let content = read(&new_mp3_file).unwrap();
+        let audio_offset = chapters[0].start_offset;
+        assert!(audio_offset > 0);
+        assert_eq!(content[audio_offset as usize], 0xFF);
+        assert_eq!(
+            audio_offset as u64,
+            probe::audio_offset(&mut Cursor::new(&content)).unwrap()
+        );
+        //32 kbps make 4 bytes a millisecond
+        for chapter in &chapters {
+            assert_eq!(chapter.start_offset, audio_offset + chapter.start_time * 4);
+            assert_eq!(chapter.end_offset, audio_offset + chapter.end_time * 4);
+        }
+        assert!(chapters
+            .windows(2)
+            .all(|pair| pair[0].start_offset < pair[1].start_offset));
+        assert_eq!(chapters[0].end_offset, chapters[1].start_offset);
+        assert!(chapters[1].end_offset as u64 <= content.len() as u64);
+
+        //Left alone unless asked for, like before the offsets were filled
+        let default_file = offsets_dir.join("default.mp3");
+        copy(test_file!("cbr.mp3"), &default_file).unwrap();
+        let new_mp3_file = apply_with_options(
+            test_file!("wav_chaps.cvs").into(),
+            default_file,
+            Mode::Sequential,
+            ApplyOptions::default(),
+        )
+        .unwrap();
+        let tag = Tag::read_from_path(new_mp3_file).unwrap();
+        assert!(tag
+            .chapters()
+            .all(|chapter| chapter.start_offset == 0 && chapter.end_offset == 0));
+
+        let vbr_file = offsets_dir.join("vbr.mp3");
+        copy(test_file!("audio.mp3"), &vbr_file).unwrap();
+        let new_mp3_file = apply_with_options(
             test_file!("valid_chaps.cvs").into(),
-            test_file!("audio.ogg").into(),
+            vbr_file,
             Mode::Sequential,
+            options,
         )
-        .is_err_and(|e| match e.downcast_ref() {
-            Some(AppliersErrors::AudioFileNotCompatible(_)) => true,
-            _ => false,
-        }))
+        .unwrap();
+        let tag = Tag::read_from_path(new_mp3_file).unwrap();
+        assert!(tag
+            .chapters()
+            .all(|chapter| chapter.start_offset == u32::MAX && chapter.end_offset == u32::MAX));
     }
 
     #[test]
-    fn test_not_cvs_seq() {
-        assert!(apply(
-            test_file!("file.txt").into(),
-            test_file!("audio.mp3").into(),
-            Mode::Sequential,
-        )
-        .is_err_and(|e| match e.downcast_ref() {
-            Some(AppliersErrors::ChaptersFileNotCompatible) => true,
-            _ => false,
-        }))
+    fn test_error_kind() {
+        let error = AppliersErrors::OutputExists("episode.mp3".to_string());
+        assert_eq!(error.kind(), "OutputExists");
+        assert_eq!(
+            AppliersErrors::ChaptersFileNotCompatible.kind(),
+            "ChaptersFileNotCompatible"
+        );
     }
 
     #[test]
-    fn test_invalid_cvs_seq() {
-        assert!(apply(
-            test_file!("invalid_chaps.cvs").into(),
+    fn test_config() {
+        let config = Config::load(test_file!("presquile.toml").as_ref()).unwrap();
+        assert_eq!(config.mode, Some(Mode::Parallel));
+
+        let options = config.merge(ApplyOptions::default());
+        assert_eq!(options.suffix.as_deref(), Some("_chapters"));
+        assert_eq!(options.id3_version, Some(Version::Id3v23));
+        assert!(options.lenient);
+
+        //Command line values win over the file ones
+        let options = config.merge(
+            ApplyOptions::default()
+                .with_suffix("_cli")
+                .with_id3_version(Version::Id3v24),
+        );
+        assert_eq!(options.suffix.as_deref(), Some("_cli"));
+        assert_eq!(options.id3_version, Some(Version::Id3v24));
+
+        let config_file = temp_dir().join("presquile_config.toml");
+        write(&config_file, "id3_version = \"2.2\"\n").unwrap();
+        assert!(
+            Config::load(&config_file).is_err_and(|e| match e.downcast_ref() {
+                Some(AppliersErrors::ConfigFile(_)) => true,
+                _ => false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_nested_toc() {
+        let nested_dir = temp_dir().join("presquile_nested_toc");
+        let _ = remove_dir_all(&nested_dir);
+        let output = nested_dir.join("episode.mp3");
+        apply_with_output(
+            test_file!("nested_chaps.cvs").into(),
             test_file!("audio.mp3").into(),
+            output.clone(),
             Mode::Sequential,
         )
-        .is_err_and(|e| match e.downcast_ref() {
-            Some(AppliersErrors::ChaptersFileNotCompatible) => true,
-            _ => false,
-        }))
+        .unwrap();
+
+        let tag = Tag::read_from_path(output).unwrap();
+        let title = |frames: &[Frame]| frames[0].content().text().unwrap().to_string();
+        let mut chapters: Vec<_> = tag.chapters().collect();
+        chapters.sort_by_key(|chapter| chapter.start_time);
+        let titles: Vec<_> = chapters
+            .iter()
+            .map(|chapter| title(&chapter.frames))
+            .collect();
+        assert_eq!(
+            titles,
+            ["Opening", "Interview", "Questions", "Closing", "Credits"]
+        );
+
+        let tocs: Vec<_> = tag.tables_of_contents().collect();
+        assert_eq!(tocs.len(), 3);
+        let toc = |id: &str| *tocs.iter().find(|toc| toc.element_id == id).unwrap();
+        assert!(toc("toc").top_level);
+        assert_eq!(toc("toc").elements, ["toc001", "toc002", "chp005"]);
+        assert!(!toc("toc001").top_level);
+        assert_eq!(title(&toc("toc001").frames), "Part One");
+        assert_eq!(toc("toc001").elements, ["chp001", "chp002"]);
+        assert_eq!(title(&toc("toc002").frames), "Part Two");
+        assert_eq!(toc("toc002").elements, ["chp003", "chp004"]);
     }
 
     #[test]
-    fn test_best_case_seq() {
-        let new_mp3_file = apply(
-            test_file!("valid_chaps.cvs").into(),
-            test_file!("audio.mp3").into(),
-            Mode::Sequential,
+    fn test_strict_columns() {
+        let load = |options: &ApplyOptions| {
+            let mut warnings = Vec::new();
+            let records = load_cvs_reporting(
+                test_file!("extra_columns_chaps.cvs").as_ref(),
+                options,
+                &mut warnings,
+            );
+            assert!(records.is_ok_and(|records| records.len() == 2));
+            warnings
+        };
+        assert!(load(&ApplyOptions::default()).is_empty());
+        assert_eq!(
+            load(&ApplyOptions::default().with_strict_columns(true)),
+            ["Speaker", "Notes"].map(|column| Warning::UnexpectedColumn {
+                column: column.to_string()
+            })
         );
-        assert!(new_mp3_file.is_ok());
 
-        let tag = Tag::read_from_path(new_mp3_file.unwrap());
-        assert!(tag.is_ok());
-        let tag = tag.unwrap();
+        let mut warnings = Vec::new();
+        let options = ApplyOptions::default().with_strict_columns(true);
+        load_cvs_reporting(
+            test_file!("valid_chaps.cvs").as_ref(),
+            &options,
+            &mut warnings,
+        )
+        .unwrap();
+        assert!(warnings.is_empty());
+    }
 
-        let chapters: Vec<_> = tag.chapters().collect();
-        assert!(!chapters.is_empty());
-        println!("{:?}", chapters);
+    #[cfg(unix)]
+    #[test]
+    fn test_step_timeout() {
+        let timeout_dir = temp_dir().join("presquile_step_timeout");
+        let _ = remove_dir_all(&timeout_dir);
+        create_dir_all(&timeout_dir).unwrap();
+        //Opening a fifo nobody writes to blocks, like a stalled network share
+        let stalled = timeout_dir.join("stalled.cvs");
+        let status = std::process::Command::new("mkfifo")
+            .arg(&stalled)
+            .status()
+            .unwrap();
+        assert!(status.success());
 
-        let ctocs: Vec<_> = tag.tables_of_contents().collect();
-        assert_eq!(ctocs.len(), 1);
-        println!("{:?}", ctocs);
+        let output = timeout_dir.join("episode.mp3");
+        let options = ApplyOptions::default()
+            .with_output(&output)
+            .with_step_timeout(Duration::from_millis(200));
+        let result = apply_with_options(
+            stalled,
+            test_file!("audio.mp3").into(),
+            Mode::Parallel,
+            options.clone(),
+        );
+        assert!(result.is_err_and(|e| match e.downcast_ref() {
+            Some(AppliersErrors::Timeout(step)) => step == "load_cvs",
+            _ => false,
+        }));
+        assert!(!output.exists());
+        assert!(staged_files(&output).is_empty());
 
-        chapters
-            .iter()
-            .zip(ctocs.last().unwrap().elements.iter())
-            .for_each(|(chap, chap_id)| assert_eq!(chap.element_id, *chap_id));
+        let new_mp3_file = apply_with_options(
+            test_file!("valid_chaps.cvs").into(),
+            test_file!("audio.mp3").into(),
+            Mode::Parallel,
+            options,
+        );
+        assert!(new_mp3_file.is_ok_and(|path| path == output));
     }
 }
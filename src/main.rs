@@ -1,32 +1,738 @@
-use clap::{Parser, Subcommand};
-use presquile::{apply, Mode};
-use std::path::PathBuf;
+use anyhow::{ensure, Result};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::{generate, Shell};
+use id3::Tag;
+use log::LevelFilter;
+use presquile::{
+    apply_with_progress, batch_glob, batch_with_options, convert_time, expand_patterns,
+    export_ffmetadata, export_podcast_json, export_webvtt, extract, format_time,
+    strip_with_options, verify, AppliersErrors, ApplyOptions, Config, Mode, Version,
+};
+use serde_json::json;
+use std::{
+    env,
+    ffi::OsString,
+    io::{self, stderr, IsTerminal, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// `println!` unless `--quiet` was given
+macro_rules! report {
+    ($($arg:tt)*) => {
+        if !QUIET.load(Ordering::Relaxed) {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// `eprintln!` unless `--quiet` was given
+macro_rules! report_error {
+    ($($arg:tt)*) => {
+        if !QUIET.load(Ordering::Relaxed) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+/// Reports `err` and exits with the status matching it
+fn fail(err: anyhow::Error) -> ! {
+    report_error!("Error \"{}\" occurred", err);
+    process::exit(exit_code(&err))
+}
+
+/// Same as [`fail`], reporting `err` as a JSON object on stdout
+fn fail_json(err: anyhow::Error) -> ! {
+    let kind = err
+        .downcast_ref::<AppliersErrors>()
+        .map_or("Other", AppliersErrors::kind);
+    println!("{}", json!({ "error": kind, "message": err.to_string() }));
+    process::exit(exit_code(&err))
+}
+
+fn exit_code(err: &anyhow::Error) -> i32 {
+    err.downcast_ref::<AppliersErrors>()
+        .map_or(1, AppliersErrors::exit_code)
+}
+
+/// A file skipped by an incremental batch, not a failure
+fn up_to_date(err: &anyhow::Error) -> bool {
+    matches!(err.downcast_ref(), Some(AppliersErrors::UpToDate(_)))
+}
+
+/// Keeps a copying percentage on a single stderr line, only when someone is watching
+fn copy_progress(copied: u64, total: u64) {
+    let mut stderr = stderr();
+    if QUIET.load(Ordering::Relaxed) || !stderr.is_terminal() {
+        return;
+    }
+    let percent = (copied * 100).checked_div(total).unwrap_or(100);
+    let _ = write!(stderr, "\rCopying {}%", percent);
+    if copied == total {
+        let _ = writeln!(stderr);
+    }
+}
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
-    /// Audition CVS Markers file
-    audition_cvs: PathBuf,
-
-    /// Mp3 file
-    mp3_file: PathBuf,
-
     #[command(subcommand)]
     command: Commands,
+
+    /// Print nothing, the exit status tells how it went
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Log each step and its timing, twice or more for more details
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Defaults for apply, batch, strip and verify [default: presquile.toml in the working directory]
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+}
+
+/// The apply flags reading the markers and turning them into chapters, shared with verify so
+/// that both read a file the same way
+#[derive(Args)]
+struct ParseArgs {
+    /// Shift every marker by this timecode, earlier when prefixed with -
+    #[arg(long, value_name = "TIMECODE", allow_hyphen_values = true, value_parser = parse_offset)]
+    start_offset: Option<i64>,
+
+    /// End every chapter where the next one starts, ignoring the Duration column
+    #[arg(long)]
+    gapless: bool,
+
+    /// Skip malformed CSV rows with a warning instead of failing
+    #[arg(long, overrides_with = "no_lenient")]
+    lenient: bool,
+
+    /// Fail on malformed CSV rows, whatever the config file says
+    #[arg(long, overrides_with = "lenient")]
+    no_lenient: bool,
+
+    /// Warn about the CSV columns that are not read into the chapters
+    #[arg(long)]
+    strict_columns: bool,
+
+    /// Read a CSV file without a header row, its columns being these in order
+    #[arg(long, value_delimiter = ',', value_name = "NAME,START,...")]
+    columns: Option<Vec<String>>,
+
+    /// Header of the column holding the chapter titles [default: Name]
+    #[arg(long, value_name = "HEADER")]
+    name_column: Option<String>,
+
+    /// Header of the column holding the marker starts [default: Start]
+    #[arg(long, value_name = "HEADER")]
+    start_column: Option<String>,
+
+    /// Only turn markers of this type into chapters, e.g. Cue [default: every marker]
+    #[arg(long)]
+    marker_type: Option<String>,
+
+    /// Prepended to the zero padded chapter ids [default: chp]
+    #[arg(long)]
+    chapter_id_prefix: Option<String>,
+
+    /// Title of the markers without a name, followed by their position [default: Chapter]
+    #[arg(long)]
+    untitled_prefix: Option<String>,
+
+    /// Add a chapter with this title before a first marker that doesn't start at zero
+    #[arg(long)]
+    intro_title: Option<String>,
+
+    /// Trim the titles, collapse their whitespace and drop their control characters
+    #[arg(long)]
+    normalize_titles: bool,
+
+    /// Warn about the chapter titles longer than this, some players truncate them
+    #[arg(long, value_name = "N")]
+    max_title_len: Option<usize>,
+}
+
+impl ParseArgs {
+    /// `options` reading the markers as asked, completed by `config`
+    fn options(self, config: &Config, options: ApplyOptions) -> ApplyOptions {
+        let options = config.merge(ApplyOptions {
+            start_offset_ms: self.start_offset,
+            gapless: self.gapless,
+            strict_columns: self.strict_columns,
+            columns: self.columns,
+            name_column: self.name_column,
+            start_column: self.start_column,
+            marker_type: self.marker_type,
+            chapter_id_prefix: self.chapter_id_prefix,
+            untitled_prefix: self.untitled_prefix,
+            intro_title: self.intro_title,
+            normalize_titles: self.normalize_titles,
+            max_title_len: self.max_title_len,
+            ..options
+        });
+        //Either flag wins over the config file
+        ApplyOptions {
+            lenient: flag(self.lenient, self.no_lenient).unwrap_or(options.lenient),
+            ..options
+        }
+    }
 }
 
+//Parsed once per run, boxing the many apply flags would only get in the way of the match
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Commands {
     /// Write chapter to mp3 id3V2 tags from Adobe Audition CSV file
-    Apply,
+    Apply {
+        /// Audition CVS Markers file, - to read it from the standard input
+        audition_cvs: PathBuf,
+
+        /// Mp3 files, several sharing the markers such as the bitrate variants of an episode,
+        /// or patterns of them such as "episode-42-*.mp3"
+        #[arg(required = true)]
+        mp3_files: Vec<PathBuf>,
+
+        /// Load, verify and copy the files in parallel
+        #[arg(long, overrides_with = "sequential")]
+        parallel: bool,
+
+        /// Process the files one step after the other, whatever the config file says
+        #[arg(long, overrides_with = "parallel")]
+        sequential: bool,
+
+        /// Give up on a step of the parallel mode still running after this many seconds
+        #[arg(long, requires = "parallel")]
+        step_timeout: Option<u64>,
+
+        /// Copy the mp3 file again up to this many times when the copy fails with an IO error
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        copy_retries: u32,
+
+        /// Milliseconds to wait before the first retry of the copy, doubled for the next ones
+        #[arg(long, value_name = "MS", requires = "copy_retries")]
+        copy_retry_delay: Option<u64>,
+
+        /// Print the chapters and the destination without writing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Print the outcome as a JSON object on stdout and nothing else
+        #[arg(long, conflicts_with = "dry_run")]
+        json: bool,
+
+        /// Replace the mp3 file with the enriched one, written to a copy renamed over it
+        #[arg(long)]
+        in_place: bool,
+
+        /// Write the chapters to a <name>.chapters.txt next to the mp3 file, leaving it untouched
+        #[arg(long, conflicts_with = "in_place")]
+        sidecar: bool,
+
+        /// Keep a .bak copy of the mp3 file before writing in place
+        #[arg(long, requires = "in_place")]
+        backup: bool,
+
+        /// Give the output the modification time of the mp3 file instead of a fresh one
+        #[arg(long)]
+        preserve_mtime: bool,
+
+        /// Replace the output file if it already exists, and the chapters already in the mp3 file
+        #[arg(long)]
+        force: bool,
+
+        #[command(flatten)]
+        parsing: ParseArgs,
+
+        /// Fail when the markers start late, end too early or past the end of the audio
+        #[arg(long)]
+        strict_span: bool,
+
+        /// ID3 version of the tag written to mp3 files [default: 2.4]
+        #[arg(long, value_enum)]
+        id3_version: Option<Id3Version>,
+
+        /// Also write an ID3v1 tag, titled like the table of contents, for very old players
+        #[arg(long)]
+        id3v1: bool,
+
+        /// Fill the chapter byte offsets when the mp3 file has a constant bitrate
+        #[arg(long)]
+        byte_offsets: bool,
+
+        /// Keep the chapters already in the mp3 file and add the new ones to them
+        #[arg(long)]
+        merge: bool,
+
+        /// Title of the table of contents [default: the album title or "Chapters"]
+        #[arg(long)]
+        toc_title: Option<String>,
+
+        /// Don't mark the chapters as meant to be played in order
+        #[arg(long)]
+        unordered_toc: bool,
+
+        /// Write the chapters without a table of contents
+        #[arg(long, conflicts_with_all = ["toc_title", "unordered_toc", "nested_toc"])]
+        no_toc: bool,
+
+        /// Don't mark the table of contents as the root one
+        #[arg(long)]
+        nested_toc: bool,
+
+        /// Appended to the mp3 file name to name the output [default: _enriched]
+        #[arg(long)]
+        suffix: Option<String>,
+
+        /// Directory the output is written to instead of next to the mp3 file
+        #[arg(long, conflicts_with = "in_place")]
+        output_dir: Option<PathBuf>,
+    },
+    /// Apply every Adobe Audition CSV file in a directory to the mp3 file sharing its name
+    Batch {
+        /// Directory holding the mp3 and the Audition CVS Markers files, or a pattern of
+        /// the mp3 files such as "episodes/*.mp3", each next to its markers file
+        input: String,
+
+        /// Process the files in parallel
+        #[arg(long, overrides_with = "sequential")]
+        parallel: bool,
+
+        /// Process the files one after the other, whatever the config file says
+        #[arg(long, overrides_with = "parallel")]
+        sequential: bool,
+
+        /// Directory the enriched files are written to instead of next to their source
+        #[arg(long)]
+        output_dir: Option<PathBuf>,
+
+        /// Skip the files whose enriched output is newer than both the mp3 and the markers
+        #[arg(long)]
+        incremental: bool,
+
+        /// Overwrite the enriched files already there
+        #[arg(long)]
+        force: bool,
+    },
+    /// Convert an Adobe Audition CSV file into a standalone chapters file
+    Export {
+        /// Audition CVS Markers file
+        audition_cvs: PathBuf,
+
+        /// Chapters file to write
+        output: PathBuf,
+
+        /// Format of the chapters file
+        #[arg(long, value_enum)]
+        format: ExportFormat,
+
+        /// Length of the audio as [[hh:]mm:]ss[.mmm], where the last chapter ends
+        #[arg(long)]
+        duration: String,
+    },
+    /// Write the chapters stored in mp3 id3V2 tags to an Adobe Audition CSV file
+    Extract {
+        /// Mp3 file
+        mp3_file: PathBuf,
+
+        /// Audition CVS Markers file to write
+        output: PathBuf,
+    },
+    /// Print the chapters and the table of contents stored in mp3 id3V2 tags
+    List {
+        /// Mp3 file
+        mp3_file: PathBuf,
+    },
+    /// Remove chapters and the table of contents from mp3 id3V2 tags
+    Strip {
+        /// Mp3 file
+        mp3_file: PathBuf,
+
+        /// Replace the mp3 file with the stripped one, written to a copy renamed over it
+        #[arg(long)]
+        in_place: bool,
+
+        /// Appended to the mp3 file name to name the output [default: _enriched]
+        #[arg(long)]
+        suffix: Option<String>,
+
+        /// Directory the output is written to instead of next to the mp3 file
+        #[arg(long, conflicts_with = "in_place")]
+        output_dir: Option<PathBuf>,
+
+        /// Overwrite the output file if it already exists
+        #[arg(long)]
+        force: bool,
+    },
+    /// Validate an Adobe Audition CSV file without writing anything
+    Verify {
+        /// Audition CVS Markers file, - to read it from the standard input
+        audition_cvs: PathBuf,
+
+        #[command(flatten)]
+        parsing: ParseArgs,
+    },
+    /// Print the completion script of a shell
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+}
+
+#[derive(Clone, ValueEnum)]
+enum Id3Version {
+    #[value(name = "2.3")]
+    V23,
+    #[value(name = "2.4")]
+    V24,
+}
+
+impl From<Id3Version> for Version {
+    fn from(version: Id3Version) -> Self {
+        match version {
+            Id3Version::V23 => Version::Id3v23,
+            Id3Version::V24 => Version::Id3v24,
+        }
+    }
+}
+
+#[derive(Clone, ValueEnum)]
+enum ExportFormat {
+    /// ffmpeg metadata file, for muxing chapters with ffmpeg
+    Ffmetadata,
+    /// WebVTT chapter cues, for HTML5 players
+    Webvtt,
+    /// Podcasting 2.0 chapters.json
+    PodcastJson,
+}
+
+fn export(
+    audition_cvs: PathBuf,
+    output: PathBuf,
+    format: ExportFormat,
+    duration: &str,
+) -> Result<()> {
+    let duration = convert_time(duration)? as f64;
+    match format {
+        ExportFormat::Ffmetadata => export_ffmetadata(audition_cvs, duration, output),
+        ExportFormat::Webvtt => export_webvtt(audition_cvs, duration, output),
+        ExportFormat::PodcastJson => export_podcast_json(audition_cvs, duration, output),
+    }
+}
+
+/// A timecode such as `0:02` or `-1:00.500`, in milliseconds
+fn parse_offset(value: &str) -> Result<i64, String> {
+    let (sign, timecode) = match value.strip_prefix('-') {
+        Some(timecode) => (-1, timecode),
+        None => (1, value),
+    };
+    convert_time(timecode)
+        .map(|ms| sign * ms as i64)
+        .map_err(|_| format!("{:?} is not a timecode", value))
+}
+
+fn list(mp3_file: &Path) -> Result<()> {
+    let tag = Tag::read_from_path(mp3_file)?;
+    let chapters: Vec<_> = tag.chapters().collect();
+    ensure!(!chapters.is_empty(), "No chapters found in {:?}", mp3_file);
+
+    for chapter in chapters {
+        let title = chapter
+            .frames
+            .iter()
+            .find(|frame| frame.id() == "TIT2")
+            .and_then(|frame| frame.content().text())
+            .unwrap_or_default();
+        report!(
+            "{}\t{} --> {}\t{}",
+            chapter.element_id,
+            format_time(chapter.start_time),
+            format_time(chapter.end_time),
+            title
+        );
+    }
+    for toc in tag.tables_of_contents() {
+        report!("{}: {}", toc.element_id, toc.elements.join(", "));
+    }
+    Ok(())
+}
+
+/// `Some` when the flag or its opposite was given, `None` leaves it to the config file
+fn flag(on: bool, off: bool) -> Option<bool> {
+    match (on, off) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+/// The mode asked for by `--parallel` or `--sequential`, the one of the config file otherwise
+fn flag_mode(parallel: bool, sequential: bool, config: &Config) -> Mode {
+    match flag(parallel, sequential) {
+        Some(true) => Mode::Parallel,
+        Some(false) => Mode::Sequential,
+        None => config.mode.unwrap_or(Mode::Sequential),
+    }
+}
+
+/// Inserts `apply` when no subcommand is named first. The `presquile <AUDITION_CVS> <MP3> apply`
+/// invocation of the first releases keeps working, its trailing `apply` is dropped
+fn with_default_command(mut args: Vec<OsString>) -> Vec<OsString> {
+    let command = Cli::command();
+    let mut rest = args.iter().enumerate().skip(1);
+    let mut first = None;
+    while let Some((idx, arg)) = rest.next() {
+        match arg.to_str() {
+            //The only global option taking a separate value
+            Some("--config") => {
+                rest.next();
+            }
+            Some(flag) if flag.starts_with('-') && flag != "-" => (),
+            _ => {
+                first = Some((idx, arg));
+                break;
+            }
+        }
+    }
+    let named = first.is_none_or(|(_, first)| {
+        first == "help"
+            || command
+                .get_subcommands()
+                .any(|subcommand| subcommand.get_name() == first)
+    });
+    if let (false, Some((idx, _))) = (named, first) {
+        if let Some(legacy) = args.iter().skip(idx + 1).position(|arg| arg == "apply") {
+            args.remove(idx + 1 + legacy);
+        }
+        args.insert(1, "apply".into());
+    }
+    args
 }
 
 fn main() {
-    let cli = Cli::parse();
+    let cli = Cli::parse_from(with_default_command(env::args_os().collect()));
+    QUIET.store(cli.quiet, Ordering::Relaxed);
+    let level = match cli.verbose {
+        _ if cli.quiet => LevelFilter::Off,
+        0 => LevelFilter::Off,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+    env_logger::Builder::new().filter_level(level).init();
     match cli.command {
-        Commands::Apply => match apply(cli.audition_cvs, cli.mp3_file, Mode::Sequential) {
-            Ok(path) => println!("Chapters written to {:?}", path),
-            Err(err) => println!("Error \"{}\" occurred", err),
-        },
+        Commands::Apply {
+            audition_cvs,
+            mp3_files,
+            parallel,
+            sequential,
+            step_timeout,
+            copy_retries,
+            copy_retry_delay,
+            dry_run,
+            json,
+            in_place,
+            sidecar,
+            backup,
+            preserve_mtime,
+            force,
+            parsing,
+            suffix,
+            output_dir,
+            strict_span,
+            id3_version,
+            id3v1,
+            byte_offsets,
+            merge,
+            toc_title,
+            unordered_toc,
+            no_toc,
+            nested_toc,
+        } => {
+            let config = match Config::discover(cli.config.as_deref()) {
+                Ok(config) => config,
+                Err(err) if json => fail_json(err),
+                Err(err) => fail(err),
+            };
+            let mode = flag_mode(parallel, sequential, &config);
+            let options = parsing.options(
+                &config,
+                ApplyOptions {
+                    step_timeout: step_timeout.map(Duration::from_secs),
+                    copy_retries,
+                    copy_retry_delay: copy_retry_delay.map(Duration::from_millis),
+                    dry_run,
+                    in_place,
+                    sidecar,
+                    backup,
+                    preserve_mtime,
+                    keep_existing: !force,
+                    keep_chapters: !force,
+                    suffix,
+                    output_dir,
+                    strict_span,
+                    id3_version: id3_version.map(Into::into),
+                    id3v1,
+                    byte_offsets,
+                    merge,
+                    toc_title,
+                    toc_ordered: Some(!unordered_toc),
+                    toc: Some(!no_toc),
+                    toc_top_level: Some(!nested_toc),
+                    ..Default::default()
+                },
+            );
+            let progress = |copied, total| {
+                if !json {
+                    copy_progress(copied, total)
+                }
+            };
+            //Shells on Windows leave patterns alone, they are expanded here
+            let mp3_files = match expand_patterns(mp3_files) {
+                Ok(mp3_files) => mp3_files,
+                Err(err) if json => fail_json(err),
+                Err(err) => fail(err),
+            };
+            for mp3_file in mp3_files {
+                let audition_cvs = audition_cvs.clone();
+                match apply_with_progress(audition_cvs, mp3_file, mode, options.clone(), progress) {
+                    Ok(report) if json => println!("{}", json!(report)),
+                    Err(err) if json => fail_json(err),
+                    Ok(report) => {
+                        for warning in &report.warnings {
+                            report!("Warning: {}", warning);
+                        }
+                        if dry_run {
+                            for line in &report.preview {
+                                report!("{}", line);
+                            }
+                            report!("Dry run, chapters would be written to {:?}", report.output);
+                        } else {
+                            report!(
+                                "{} chapters written to {:?}",
+                                report.chapter_count,
+                                report.output
+                            );
+                        }
+                    }
+                    Err(err) => fail(err),
+                }
+            }
+        }
+        Commands::Batch {
+            input,
+            parallel,
+            sequential,
+            output_dir,
+            incremental,
+            force,
+        } => {
+            let config = Config::discover(cli.config.as_deref()).unwrap_or_else(|err| fail(err));
+            let mode = flag_mode(parallel, sequential, &config);
+            //Print each file as soon as it's done rather than once the whole directory is
+            let progress = |file: &Path, result: &Result<PathBuf>| match result {
+                Ok(path) => report!("{:?}: chapters written to {:?}", file, path),
+                Err(err) if up_to_date(err) => report!("{:?}: up to date, skipped", file),
+                Err(err) => report_error!("{:?}: error \"{}\" occurred", file, err),
+            };
+            let options = config.merge(ApplyOptions {
+                output_dir,
+                incremental,
+                keep_existing: !force,
+                ..Default::default()
+            });
+            //Shells on Windows leave patterns alone, they are expanded here
+            let results = match Path::new(&input).is_dir() {
+                true => batch_with_options(input.into(), mode, &options, progress),
+                false => batch_glob(&input, mode, &options, progress),
+            };
+            match results {
+                Ok(results) => {
+                    let failed = |result: &Result<PathBuf>| {
+                        result.as_ref().is_err_and(|err| !up_to_date(err))
+                    };
+                    if results.iter().any(|(_, result)| failed(result)) {
+                        process::exit(1);
+                    }
+                }
+                Err(err) => {
+                    fail(err);
+                }
+            }
+        }
+        Commands::Export {
+            audition_cvs,
+            output,
+            format,
+            duration,
+        } => {
+            if let Err(err) = export(audition_cvs, output.clone(), format, &duration) {
+                fail(err);
+            }
+            report!("Chapters exported to {:?}", output);
+        }
+        Commands::Extract { mp3_file, output } => {
+            if let Err(err) = extract(mp3_file, output.clone()) {
+                fail(err);
+            }
+            report!("Chapters extracted to {:?}", output);
+        }
+        Commands::List { mp3_file } => {
+            if let Err(err) = list(&mp3_file) {
+                fail(err);
+            }
+        }
+        Commands::Strip {
+            mp3_file,
+            in_place,
+            suffix,
+            output_dir,
+            force,
+        } => {
+            let config = Config::discover(cli.config.as_deref()).unwrap_or_else(|err| fail(err));
+            let options = config.merge(ApplyOptions {
+                in_place,
+                suffix,
+                output_dir,
+                keep_existing: !force,
+                ..Default::default()
+            });
+            match strip_with_options(mp3_file.clone(), &options) {
+                Ok(Some(path)) => report!("Chapters removed, written to {:?}", path),
+                Ok(None) => report!("No chapters to remove in {:?}", mp3_file),
+                Err(err) => fail(err),
+            }
+        }
+        Commands::Verify {
+            audition_cvs,
+            parsing,
+        } => {
+            let config = Config::discover(cli.config.as_deref()).unwrap_or_else(|err| fail(err));
+            let options = parsing.options(&config, ApplyOptions::default());
+            match verify(audition_cvs, &options) {
+                Ok(report) => {
+                    report!(
+                        "{} chapters spanning {}",
+                        report.chapters,
+                        format_time(report.span)
+                    );
+                    for warning in report.warnings {
+                        report!("Warning: {}", warning);
+                    }
+                }
+                Err(err) => {
+                    fail(err);
+                }
+            }
+        }
+        Commands::Completions { shell } => {
+            let mut command = Cli::command();
+            let name = command.get_name().to_string();
+            generate(shell, &mut command, name, &mut io::stdout());
+        }
     }
 }
@@ -1,10 +1,68 @@
 use serde::Deserialize;
+use std::path::PathBuf;
 
 pub type AuditionCvsRecords = Vec<AuditionCvsRecord>;
 
-#[derive(Debug, Deserialize)]
+/// Labels of the columns every row needs, localized exports included
+pub const NAME_LABELS: [&str; 2] = ["Name", "Nome"];
+pub const START_LABELS: [&str; 3] = ["Start", "Inizio", "Beginn"];
+/// Labels of every column read into a record, plus the `Time Format` Audition always exports
+pub const KNOWN_LABELS: [&str; 24] = [
+    "Name",
+    "Nome",
+    "Start",
+    "Inizio",
+    "Beginn",
+    "Duration",
+    "Durata",
+    "Dauer",
+    "Description",
+    "Descrizione",
+    "Beschreibung",
+    "Img",
+    "Image",
+    "Url",
+    "URL",
+    "Level",
+    "Id",
+    "ID",
+    "Type",
+    "Tipo",
+    "Typ",
+    "Time Format",
+    "Formato ora",
+    "Zeitformat",
+];
+
+/// Localized Audition exports label the columns in Italian or German
+#[derive(Debug, Default, Deserialize, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub struct AuditionCvsRecord {
+    #[serde(alias = "Nome")]
     pub name: String,
+    #[serde(alias = "Inizio", alias = "Beginn")]
     pub start: String,
+    #[serde(default, alias = "Durata", alias = "Dauer")]
+    pub duration: Option<String>,
+    /// Chapter subtitle, written as `TIT3` next to the `TIT2` title
+    #[serde(default, alias = "Descrizione", alias = "Beschreibung")]
+    pub description: Option<String>,
+    /// Chapter artwork url, only used by the Podcasting 2.0 export
+    #[serde(default)]
+    pub img: Option<String>,
+    /// Chapter artwork file embedded as `APIC`, relative to the CSV file
+    #[serde(default)]
+    pub image: Option<PathBuf>,
+    /// Chapter link, written as a `WXXX` frame and used by the Podcasting 2.0 export
+    #[serde(default, alias = "URL")]
+    pub url: Option<String>,
+    /// Element id of the chapter, numbered after its position if unset
+    #[serde(default, alias = "ID")]
+    pub id: Option<String>,
+    /// Depth of the marker, `2` for the chapters of the `1` marker before them
+    #[serde(default)]
+    pub level: Option<u8>,
+    /// Audition marker kind, such as `Cue` or `Subclip`
+    #[serde(default, rename = "Type", alias = "Tipo", alias = "Typ")]
+    pub marker_type: Option<String>,
 }
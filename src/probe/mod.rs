@@ -0,0 +1,3 @@
+mod mp3;
+
+pub use mp3::{audio_offset, mp3_constant_bitrate, mp3_duration};
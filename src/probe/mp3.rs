@@ -0,0 +1,177 @@
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+
+//Bitrates in kbps by bitrate index, the first and last index are free format and invalid
+const BITRATES_V1: [[u32; 16]; 3] = [
+    [
+        0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448, 0,
+    ],
+    [
+        0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384, 0,
+    ],
+    [
+        0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 0,
+    ],
+];
+const BITRATES_V2: [[u32; 16]; 3] = [
+    [
+        0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256, 0,
+    ],
+    [
+        0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160, 0,
+    ],
+    [
+        0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160, 0,
+    ],
+];
+const SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+
+struct FrameHeader {
+    mpeg1: bool,
+    bitrate: u32,
+    mono: bool,
+    sample_rate: u32,
+    samples: u32,
+    length: usize,
+}
+
+impl FrameHeader {
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        let [0xFF, b1, b2, b3, ..] = *bytes else {
+            return None;
+        };
+        if b1 & 0xE0 != 0xE0 {
+            return None;
+        }
+        //Version 0 is MPEG 2.5, 2 is MPEG 2 and 3 is MPEG 1, layer 3 is Layer I
+        let (version, layer) = ((b1 >> 3) & 0b11, (b1 >> 1) & 0b11);
+        let (bitrate, rate) = ((b2 >> 4) as usize, ((b2 >> 2) & 0b11) as usize);
+        if version == 1 || layer == 0 || rate == 3 {
+            return None;
+        }
+        let mpeg1 = version == 3;
+        let layer = (3 - layer) as usize;
+        let bitrate = match mpeg1 {
+            true => BITRATES_V1[layer][bitrate],
+            false => BITRATES_V2[layer][bitrate],
+        } * 1000;
+        if bitrate == 0 {
+            return None;
+        }
+        let sample_rate = SAMPLE_RATES[rate]
+            >> match version {
+                3 => 0,
+                2 => 1,
+                _ => 2,
+            };
+        let padding = ((b2 >> 1) & 1) as u32;
+        let (samples, length) = match layer {
+            0 => (384, (12 * bitrate / sample_rate + padding) * 4),
+            1 => (1152, 144 * bitrate / sample_rate + padding),
+            _ if mpeg1 => (1152, 144 * bitrate / sample_rate + padding),
+            _ => (576, 72 * bitrate / sample_rate + padding),
+        };
+        Some(Self {
+            mpeg1,
+            bitrate,
+            mono: b3 >> 6 == 0b11,
+            sample_rate,
+            samples,
+            length: length as usize,
+        })
+    }
+
+    /// Frame count stored by encoders in a Xing, Info or VBRI header in place of the first frame
+    fn vbr_frames(&self, frame: &[u8]) -> Option<u32> {
+        let side_info = match (self.mpeg1, self.mono) {
+            (true, false) => 32,
+            (true, true) | (false, false) => 17,
+            (false, true) => 9,
+        };
+        let read = |offset: usize| {
+            let bytes = frame.get(offset..offset + 4)?;
+            Some(u32::from_be_bytes(bytes.try_into().ok()?))
+        };
+        match frame.get(4 + side_info..8 + side_info) {
+            Some(b"Xing" | b"Info") if read(8 + side_info)? & 1 == 1 => read(12 + side_info),
+            _ if frame.get(36..40) == Some(b"VBRI") => read(50),
+            _ => None,
+        }
+    }
+}
+
+/// Offset of the first byte past the ID3v2 tag, counted from the start of the file
+pub fn audio_offset(reader: &mut (impl Read + Seek)) -> io::Result<u64> {
+    let mut header = [0; 10];
+    let mut offset = 0;
+    reader.seek(SeekFrom::Start(0))?;
+    if reader.read_exact(&mut header).is_ok() && header.starts_with(b"ID3") {
+        let size = header[6..10]
+            .iter()
+            .fold(0, |size, byte| size << 7 | (*byte & 0x7F) as u64);
+        let footer = if header[5] & 0x10 != 0 { 10 } else { 0 };
+        offset = 10 + size + footer;
+    }
+    //Some taggers pad past the declared size
+    reader.seek(SeekFrom::Start(offset))?;
+    let padding = BufReader::new(reader)
+        .bytes()
+        .take_while(|byte| matches!(byte, Ok(0)))
+        .count();
+    Ok(offset + padding as u64)
+}
+
+/// Length in seconds of an mp3 file, read from its frame headers.
+/// `None` unless the audio starts with a valid frame right after the ID3v2 tag
+pub fn mp3_duration(reader: &mut (impl Read + Seek)) -> io::Result<Option<f64>> {
+    let offset = audio_offset(reader)?;
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut reader = BufReader::new(reader);
+    let mut header = [0; 4];
+    if reader.read_exact(&mut header).is_err() {
+        return Ok(None);
+    }
+    let Some(first) = FrameHeader::parse(&header) else {
+        return Ok(None);
+    };
+    //The VBR header takes the place of the audio of the first frame
+    let mut frame = header.to_vec();
+    (&mut reader)
+        .take(first.length.saturating_sub(4) as u64)
+        .read_to_end(&mut frame)?;
+    if let Some(frames) = first.vbr_frames(&frame) {
+        return Ok(Some(
+            frames as f64 * first.samples as f64 / first.sample_rate as f64,
+        ));
+    }
+
+    //Without a VBR header every frame has to be visited, their bitrates may differ
+    let mut seconds = first.samples as f64 / first.sample_rate as f64;
+    while reader.read_exact(&mut header).is_ok() {
+        let Some(frame) = FrameHeader::parse(&header) else {
+            break;
+        };
+        seconds += frame.samples as f64 / frame.sample_rate as f64;
+        reader.seek_relative(frame.length as i64 - 4)?;
+    }
+    Ok(Some(seconds))
+}
+
+/// Bitrate in bits per second of an mp3 file whose frames all share it, only their headers are read.
+/// `None` for variable bitrate files, their byte offsets can't be told from the time
+pub fn mp3_constant_bitrate(reader: &mut (impl Read + Seek)) -> io::Result<Option<u32>> {
+    let offset = audio_offset(reader)?;
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut reader = BufReader::new(reader);
+    let mut header = [0; 4];
+    let mut bitrate = None;
+    while reader.read_exact(&mut header).is_ok() {
+        let Some(frame) = FrameHeader::parse(&header) else {
+            break;
+        };
+        if *bitrate.get_or_insert(frame.bitrate) != frame.bitrate {
+            return Ok(None);
+        }
+        reader.seek_relative(frame.length as i64 - 4)?;
+    }
+    Ok(bitrate)
+}
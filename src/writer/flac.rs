@@ -0,0 +1,34 @@
+use anyhow::Result;
+use id3::frame::Chapter;
+use std::path::Path;
+
+use super::ChapterWriter;
+use crate::{chapter_title, format_time};
+
+/// Writes `CHAPTERnnn`/`CHAPTERnnnNAME` VorbisComment fields
+pub struct FlacChapterWriter;
+
+impl ChapterWriter for FlacChapterWriter {
+    fn write(&self, chapters: &[Chapter], _toc_title: &str, destination: &Path) -> Result<()> {
+        let mut tag = metaflac::Tag::read_from_path(destination)?;
+        let comments = tag.vorbis_comments_mut();
+
+        //Drop chapters left over from a previous run before numbering the new ones
+        let stale: Vec<_> = comments
+            .comments
+            .keys()
+            .filter(|key| key.starts_with("CHAPTER"))
+            .cloned()
+            .collect();
+        stale.iter().for_each(|key| comments.remove(key));
+
+        for (idx, chapter) in chapters.iter().enumerate() {
+            let key = format!("CHAPTER{:03}", idx + 1);
+            comments.set(key.clone() + "NAME", vec![chapter_title(chapter)]);
+            comments.set(key, vec![format_time(chapter.start_time)]);
+        }
+
+        tag.save()?;
+        Ok(())
+    }
+}
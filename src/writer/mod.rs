@@ -0,0 +1,34 @@
+use anyhow::Result;
+use id3::frame::Chapter;
+use std::path::Path;
+
+mod flac;
+mod mp3;
+mod mp4;
+mod ogg;
+mod wav;
+
+pub use flac::FlacChapterWriter;
+pub use mp3::Mp3ChapterWriter;
+pub use mp4::Mp4ChapterWriter;
+#[cfg(test)]
+pub use ogg::ogg_comments;
+pub use ogg::OggChapterWriter;
+#[cfg(test)]
+pub use wav::cue_points;
+pub use wav::WavChapterWriter;
+
+/// Chapters starting from `start_time` up to `end_time`, nested under their own `CTOC` in mp3
+/// files and flat everywhere else
+#[derive(Clone, Debug, PartialEq)]
+pub struct Part {
+    pub title: String,
+    pub start_time: u32,
+    pub end_time: u32,
+}
+
+/// Serializes the chapters produced by `build_chapters` into a specific container
+pub trait ChapterWriter {
+    /// `toc_title` names the table of contents, ignored by the containers without one
+    fn write(&self, chapters: &[Chapter], toc_title: &str, destination: &Path) -> Result<()>;
+}
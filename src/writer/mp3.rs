@@ -0,0 +1,199 @@
+use anyhow::Result;
+use id3::{
+    frame::{Chapter, TableOfContents},
+    no_tag_ok, v1, Frame, Tag, TagLike, Version,
+};
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+};
+
+use super::{ChapterWriter, Part};
+use crate::{chapter_id, probe, NO_OFFSET};
+
+/// Writes ID3v2 `CHAP` frames plus, unless `toc` is off, a `CTOC` referencing them
+pub struct Mp3ChapterWriter {
+    pub version: Version,
+    pub toc_id: String,
+    pub toc_top_level: bool,
+    pub toc_ordered: bool,
+    /// Leave the `CTOC` out for the players that only read `CHAP` frames
+    pub toc: bool,
+    /// Keep the chapters already in the file and renumber them together with the new ones
+    pub merge: bool,
+    pub chapter_id_prefix: String,
+    /// Groups of chapters referenced by the main `CTOC` in place of their chapters
+    pub parts: Vec<Part>,
+    /// Also write an ID3v1 tag, titled like the `CTOC`, for the players that only read that
+    pub id3v1: bool,
+    /// The chapter offsets were counted from the first audio frame, the tag size is added to them
+    pub byte_offsets: bool,
+}
+
+impl Mp3ChapterWriter {
+    /// Returns the tag stripped of its chapters, along with the chapters it had
+    fn load_tag(mp3_file: &Path) -> Result<(Tag, Vec<Chapter>)> {
+        //Keep every other frame of the source, only the chapters get replaced
+        let mut tag = no_tag_ok(Tag::read_from_path(mp3_file))?.unwrap_or_default();
+        let existing = tag.chapters().cloned().collect();
+        tag.remove_all_chapters();
+        tag.remove_all_tables_of_contents();
+        Ok((tag, existing))
+    }
+
+    /// Renumbers the chapters together, the new ids read from an `Id` column are kept as they are
+    fn merge(&self, existing: Vec<Chapter>, chapters: &[Chapter]) -> Vec<Chapter> {
+        let mut merged: Vec<_> = existing
+            .into_iter()
+            .map(|chapter| (true, chapter))
+            .chain(
+                chapters
+                    .iter()
+                    .map(|chapter| (self.numbered(&chapter.element_id), chapter.clone())),
+            )
+            .collect();
+        //Stable sort: on equal starts the chapters already in the file come first
+        merged.sort_by_key(|(_, chapter)| chapter.start_time);
+        let count = merged.len();
+        merged
+            .into_iter()
+            .enumerate()
+            .map(|(idx, (renumber, mut chapter))| {
+                if renumber {
+                    chapter.element_id = chapter_id(&self.chapter_id_prefix, idx, count);
+                }
+                chapter
+            })
+            .collect()
+    }
+
+    /// Whether `element_id` was made by [`chapter_id`] rather than read from an `Id` column
+    fn numbered(&self, element_id: &str) -> bool {
+        element_id
+            .strip_prefix(&self.chapter_id_prefix)
+            .is_some_and(|number| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()))
+    }
+
+    fn build_tag(&self, mut tag: Tag, chapters: &[Chapter], toc_title: &str) -> Tag {
+        for chapter in chapters {
+            tag.add_frame(chapter.clone());
+        }
+        if !self.toc {
+            return tag;
+        }
+
+        let mut elements = Vec::new();
+        let mut part_elements = vec![Vec::new(); self.parts.len()];
+        for chapter in chapters {
+            let part = self
+                .parts
+                .iter()
+                .position(|part| (part.start_time..part.end_time).contains(&chapter.start_time));
+            match part {
+                Some(idx) => {
+                    //The part takes the place of its first chapter
+                    if part_elements[idx].is_empty() {
+                        elements.push(chapter_id(&self.toc_id, idx, self.parts.len()));
+                    }
+                    part_elements[idx].push(chapter.element_id.clone());
+                }
+                None => elements.push(chapter.element_id.clone()),
+            }
+        }
+        for (idx, (part, part_elements)) in self.parts.iter().zip(part_elements).enumerate() {
+            if part_elements.is_empty() {
+                continue;
+            }
+            tag.add_frame(TableOfContents {
+                element_id: chapter_id(&self.toc_id, idx, self.parts.len()),
+                top_level: false,
+                ordered: self.toc_ordered,
+                elements: part_elements,
+                frames: vec![Frame::text("TIT2", part.title.clone())],
+            });
+        }
+        tag.add_frame(TableOfContents {
+            element_id: self.toc_id.clone(),
+            top_level: self.toc_top_level,
+            ordered: self.toc_ordered,
+            elements,
+            frames: vec![Frame::text("TIT2", toc_title.to_string())],
+        });
+
+        tag
+    }
+}
+
+impl ChapterWriter for Mp3ChapterWriter {
+    fn write(&self, chapters: &[Chapter], toc_title: &str, destination: &Path) -> Result<()> {
+        let (source_tag, existing) = Self::load_tag(destination)?;
+        let build = |chapters: &[Chapter]| {
+            let chapters = match self.merge {
+                true => self.merge(existing.clone(), chapters),
+                false => chapters.to_vec(),
+            };
+            self.build_tag(source_tag.clone(), &chapters, toc_title)
+        };
+        let mut tag = build(chapters);
+        tag.write_to_path(destination, self.version)?;
+        //Byte offsets count from the start of the file, the size of the tag is known only now
+        let filled = chapters
+            .iter()
+            .any(|chapter| chapter.start_offset != NO_OFFSET);
+        if self.byte_offsets && filled {
+            let audio_offset = probe::audio_offset(&mut File::open(destination)?)?;
+            let shift = |offset: u32| match offset {
+                NO_OFFSET => NO_OFFSET,
+                offset => u32::try_from(offset as u64 + audio_offset).unwrap_or(NO_OFFSET),
+            };
+            let shifted: Vec<_> = chapters
+                .iter()
+                .map(|chapter| Chapter {
+                    start_offset: shift(chapter.start_offset),
+                    end_offset: shift(chapter.end_offset),
+                    ..chapter.clone()
+                })
+                .collect();
+            //Offsets are fixed size fields, the tag keeps its size and is rewritten in place
+            tag = build(&shifted);
+            tag.write_to_path(destination, self.version)?;
+        }
+        if self.id3v1 {
+            //A previous ID3v1 tag would otherwise be read in place of the new one
+            v1::Tag::remove_from_path(destination)?;
+            let mut file = OpenOptions::new().append(true).open(destination)?;
+            file.write_all(&id3v1_tag(&tag, toc_title))?;
+        }
+        Ok(())
+    }
+}
+
+/// The 128 bytes of an ID3v1.1 tag carrying what fits of the v2 one
+fn id3v1_tag(tag: &Tag, title: &str) -> Vec<u8> {
+    let mut out = b"TAG".to_vec();
+    push_field(&mut out, title, 30);
+    push_field(&mut out, tag.artist().unwrap_or_default(), 30);
+    push_field(&mut out, tag.album().unwrap_or_default(), 30);
+    let year = tag.year().map(|year| year.to_string()).unwrap_or_default();
+    push_field(&mut out, &year, 4);
+    //Empty comment, its last byte holds the track behind a zero
+    push_field(&mut out, "", 29);
+    out.push(
+        tag.track()
+            .and_then(|track| u8::try_from(track).ok())
+            .unwrap_or(0),
+    );
+    //No genre
+    out.push(255);
+    out
+}
+
+/// Latin-1 text cut or zero padded to `len` bytes, with `?` for what Latin-1 can't hold
+fn push_field(out: &mut Vec<u8>, text: &str, len: usize) {
+    let bytes = text
+        .chars()
+        .map(|char| u8::try_from(u32::from(char)).unwrap_or(b'?'))
+        .chain(std::iter::repeat(0));
+    out.extend(bytes.take(len));
+}
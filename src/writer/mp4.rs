@@ -0,0 +1,26 @@
+use anyhow::Result;
+use id3::frame::Chapter;
+use std::{path::Path, time::Duration};
+
+use super::ChapterWriter;
+use crate::chapter_title;
+
+/// Writes a Nero-style chapter list (`chpl` atom) for MP4/M4A/M4B files
+pub struct Mp4ChapterWriter;
+
+impl ChapterWriter for Mp4ChapterWriter {
+    fn write(&self, chapters: &[Chapter], _toc_title: &str, destination: &Path) -> Result<()> {
+        let mut tag = mp4ameta::Tag::read_from_path(destination)?;
+        *tag.chapter_list_mut() = chapters
+            .iter()
+            .map(|chapter| {
+                mp4ameta::Chapter::new(
+                    Duration::from_millis(chapter.start_time.into()),
+                    chapter_title(chapter),
+                )
+            })
+            .collect();
+        tag.write_to_path(destination)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,356 @@
+use anyhow::{ensure, Result};
+use id3::frame::Chapter;
+use std::{
+    fs::{read, write},
+    path::Path,
+};
+
+use super::ChapterWriter;
+use crate::{chapter_title, format_time, AppliersErrors};
+
+/// Rewrites the comment header of Ogg Vorbis and Opus streams with `CHAPTERnnn`/`CHAPTERnnnNAME`
+/// fields, the same VorbisComment convention used for FLAC
+pub struct OggChapterWriter;
+
+impl ChapterWriter for OggChapterWriter {
+    fn write(&self, chapters: &[Chapter], _toc_title: &str, destination: &Path) -> Result<()> {
+        let content = read(destination)?;
+        let headers = OggHeaders::parse(&content);
+        ensure!(
+            headers.is_some(),
+            AppliersErrors::AudioFileNotCompatible(
+                destination.display().to_string(),
+                "Ogg".to_string()
+            )
+        );
+        let OggHeaders {
+            pages,
+            mut packets,
+            audio_page,
+            codec,
+        } = headers.unwrap();
+
+        let (vendor, comments, trailing) = split_comments(&packets[1][codec.magic().len()..])
+            .ok_or_else(|| {
+                AppliersErrors::AudioFileNotCompatible(
+                    destination.display().to_string(),
+                    "Ogg".to_string(),
+                )
+            })?;
+
+        //Drop chapters left over from a previous run before numbering the new ones
+        let mut comments: Vec<_> = comments
+            .into_iter()
+            .filter(|comment| !comment.to_ascii_uppercase().starts_with(b"CHAPTER"))
+            .map(<[u8]>::to_vec)
+            .collect();
+        for (idx, chapter) in chapters.iter().enumerate() {
+            let key = format!("CHAPTER{:03}", idx + 1);
+            comments.push(format!("{key}={}", format_time(chapter.start_time)).into_bytes());
+            comments.push(format!("{key}NAME={}", chapter_title(chapter)).into_bytes());
+        }
+
+        let mut packet = codec.magic().to_vec();
+        packet.extend((vendor.len() as u32).to_le_bytes());
+        packet.extend(vendor);
+        packet.extend((comments.len() as u32).to_le_bytes());
+        for comment in &comments {
+            packet.extend((comment.len() as u32).to_le_bytes());
+            packet.extend(comment);
+        }
+        packet.extend(trailing);
+        packets[1] = packet;
+
+        //The identification header keeps its own page, the others grow or shrink as needed
+        let mut out = Vec::with_capacity(content.len());
+        push_page(&mut out, &pages[0]);
+        let sequence = paginate(&mut out, &pages[0], &packets[1..]);
+        for (sequence, page) in (sequence..).zip(&pages[audio_page..]) {
+            match sequence == page.sequence {
+                //Same bytes as in the source, so the checksum still holds
+                true => serialize_page(&mut out, page, page.checksum),
+                false => {
+                    push_page(&mut out, &Page { sequence, ..*page });
+                }
+            }
+        }
+
+        write(destination, out)?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Codec {
+    Vorbis,
+    Opus,
+}
+
+impl Codec {
+    fn detect(identification: &[u8]) -> Option<Codec> {
+        if identification.starts_with(b"\x01vorbis") {
+            Some(Codec::Vorbis)
+        } else if identification.starts_with(b"OpusHead") {
+            Some(Codec::Opus)
+        } else {
+            None
+        }
+    }
+
+    /// Identification, comment and, for Vorbis, setup header
+    fn header_packets(&self) -> usize {
+        match self {
+            Codec::Vorbis => 3,
+            Codec::Opus => 2,
+        }
+    }
+
+    fn magic(&self) -> &'static [u8] {
+        match self {
+            Codec::Vorbis => b"\x03vorbis",
+            Codec::Opus => b"OpusTags",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Page<'a> {
+    flags: u8,
+    granule: u64,
+    serial: u32,
+    sequence: u32,
+    checksum: u32,
+    segments: &'a [u8],
+    data: &'a [u8],
+}
+
+impl Page<'_> {
+    fn intact(&self) -> bool {
+        push_page(&mut Vec::new(), self) == self.checksum
+    }
+}
+
+struct OggHeaders<'a> {
+    pages: Vec<Page<'a>>,
+    packets: Vec<Vec<u8>>,
+    /// Index of the first page past the header packets
+    audio_page: usize,
+    codec: Codec,
+}
+
+impl<'a> OggHeaders<'a> {
+    /// Pages and header packets of a single stream Vorbis or Opus file, none for anything else
+    fn parse(content: &'a [u8]) -> Option<OggHeaders<'a>> {
+        let pages = ogg_pages(content);
+        let serial = pages.first()?.serial;
+        //Chained or multiplexed streams would need their own headers rewritten too
+        if pages.iter().any(|page| page.serial != serial) {
+            return None;
+        }
+
+        let mut packets = Vec::new();
+        let mut packet = Vec::new();
+        let mut codec = None;
+        for (idx, page) in pages.iter().enumerate() {
+            let mut offset = 0;
+            for (segment, size) in page.segments.iter().enumerate() {
+                let size = *size as usize;
+                packet.extend(&page.data[offset..offset + size]);
+                offset += size;
+                if size == 255 {
+                    continue;
+                }
+                packets.push(std::mem::take(&mut packet));
+                let codec = *codec.get_or_insert(Codec::detect(&packets[0])?);
+                if packets.len() == codec.header_packets() {
+                    //Audio is expected to start on a fresh page
+                    if segment + 1 != page.segments.len() || !packets[1].starts_with(codec.magic())
+                    {
+                        return None;
+                    }
+                    //Damaged headers are better left alone than rewritten with a valid checksum
+                    if pages[..=idx].iter().any(|page| !page.intact()) {
+                        return None;
+                    }
+                    return Some(OggHeaders {
+                        pages,
+                        packets,
+                        audio_page: idx + 1,
+                        codec,
+                    });
+                }
+            }
+            //The identification header is alone on the first page
+            if idx == 0 && packets.len() != 1 {
+                return None;
+            }
+        }
+        None
+    }
+}
+
+fn ogg_pages(content: &[u8]) -> Vec<Page<'_>> {
+    let mut pages = Vec::new();
+    let mut offset = 0;
+    while let Some(header) = content.get(offset..offset + 27) {
+        if !header.starts_with(b"OggS") {
+            break;
+        }
+        let segments = match content.get(offset + 27..offset + 27 + header[26] as usize) {
+            Some(segments) => segments,
+            None => break,
+        };
+        let start = offset + 27 + segments.len();
+        let size = segments.iter().map(|size| *size as usize).sum::<usize>();
+        let data = match content.get(start..start + size) {
+            Some(data) => data,
+            None => break,
+        };
+        pages.push(Page {
+            flags: header[5],
+            granule: u64::from_le_bytes(header[6..14].try_into().unwrap()),
+            serial: u32::from_le_bytes(header[14..18].try_into().unwrap()),
+            sequence: u32::from_le_bytes(header[18..22].try_into().unwrap()),
+            checksum: u32::from_le_bytes(header[22..26].try_into().unwrap()),
+            segments,
+            data,
+        });
+        offset = start + size;
+    }
+    pages
+}
+
+/// Vendor, comments and whatever follows them
+type CommentHeader<'a> = (&'a [u8], Vec<&'a [u8]>, &'a [u8]);
+
+/// Fields of a comment header stripped of its magic
+fn split_comments(mut content: &[u8]) -> Option<CommentHeader<'_>> {
+    let vendor = take_field(&mut content)?;
+    let count = take_u32(&mut content)?;
+    let comments = (0..count)
+        .map(|_| take_field(&mut content))
+        .collect::<Option<_>>()?;
+    Some((vendor, comments, content))
+}
+
+fn take_u32(content: &mut &[u8]) -> Option<u32> {
+    let value = u32::from_le_bytes(content.get(..4)?.try_into().unwrap());
+    *content = &content[4..];
+    Some(value)
+}
+
+/// A length prefixed field
+fn take_field<'a>(content: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let len = take_u32(content)? as usize;
+    let field = content.get(..len)?;
+    *content = &content[len..];
+    Some(field)
+}
+
+/// Lays `packets` out on as many pages as they need after the `first` one, returning the next
+/// sequence number
+fn paginate(out: &mut Vec<u8>, first: &Page, packets: &[Vec<u8>]) -> u32 {
+    let mut sequence = first.sequence + 1;
+    let mut segments = Vec::new();
+    let mut data = Vec::new();
+    let mut continued = false;
+    let mut flush = |segments: &[u8], data: &[u8], continued: bool, sequence: u32| {
+        let page = Page {
+            flags: continued as u8,
+            granule: 0,
+            sequence,
+            segments,
+            data,
+            ..*first
+        };
+        push_page(out, &page);
+    };
+    for packet in packets {
+        let sizes =
+            std::iter::repeat_n(255, packet.len() / 255).chain([(packet.len() % 255) as u8]);
+        let mut offset = 0;
+        for size in sizes {
+            segments.push(size);
+            data.extend(&packet[offset..offset + size as usize]);
+            offset += size as usize;
+            if segments.len() == 255 {
+                flush(&segments, &data, continued, sequence);
+                sequence += 1;
+                continued = size == 255;
+                segments.clear();
+                data.clear();
+            }
+        }
+    }
+    if !segments.is_empty() {
+        flush(&segments, &data, continued, sequence);
+        sequence += 1;
+    }
+    sequence
+}
+
+/// Serializes `page` with a freshly computed checksum, returned as well
+fn push_page(out: &mut Vec<u8>, page: &Page) -> u32 {
+    let start = out.len();
+    serialize_page(out, page, 0);
+    let checksum = crc(&out[start..]);
+    out[start + 22..start + 26].copy_from_slice(&checksum.to_le_bytes());
+    checksum
+}
+
+fn serialize_page(out: &mut Vec<u8>, page: &Page, checksum: u32) {
+    out.extend(b"OggS\0");
+    out.push(page.flags);
+    out.extend(page.granule.to_le_bytes());
+    out.extend(page.serial.to_le_bytes());
+    out.extend(page.sequence.to_le_bytes());
+    out.extend(checksum.to_le_bytes());
+    out.push(page.segments.len() as u8);
+    out.extend(page.segments);
+    out.extend(page.data);
+}
+
+/// CRC-32 of the Ogg spec: polynomial 0x04c11db7, no reflection, no final xor
+fn crc(page: &[u8]) -> u32 {
+    page.iter().fold(0, |crc, byte| {
+        crc << 8 ^ CRC_TABLE[((crc >> 24) as u8 ^ byte) as usize]
+    })
+}
+
+/// Remainder of every byte shifted through the polynomial, one lookup per byte instead of eight
+/// shifts
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut idx = 0;
+    while idx < 256 {
+        let mut crc = (idx as u32) << 24;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = match crc & 0x8000_0000 {
+                0 => crc << 1,
+                _ => crc << 1 ^ 0x04c1_1db7,
+            };
+            bit += 1;
+        }
+        table[idx] = crc;
+        idx += 1;
+    }
+    table
+};
+
+/// Comments of the stream, none if it can't be parsed or a page fails its checksum
+#[cfg(test)]
+pub fn ogg_comments(content: &[u8]) -> Option<Vec<String>> {
+    let headers = OggHeaders::parse(content)?;
+    if !headers.pages.iter().all(Page::intact) {
+        return None;
+    }
+    let magic = headers.codec.magic().len();
+    let (_, comments, _) = split_comments(&headers.packets[1][magic..])?;
+    Some(
+        comments
+            .into_iter()
+            .map(|comment| String::from_utf8_lossy(comment).to_string())
+            .collect(),
+    )
+}
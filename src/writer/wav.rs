@@ -0,0 +1,133 @@
+use anyhow::{ensure, Result};
+use id3::frame::Chapter;
+use std::{
+    fs::{read, write},
+    path::Path,
+};
+
+use super::ChapterWriter;
+use crate::{chapter_title, AppliersErrors};
+
+/// Writes a `cue ` chunk with one cue point per chapter and names them in a `LIST`/`adtl`
+/// chunk of `labl` entries, the convention of most WAV editors
+pub struct WavChapterWriter;
+
+impl ChapterWriter for WavChapterWriter {
+    fn write(&self, chapters: &[Chapter], _toc_title: &str, destination: &Path) -> Result<()> {
+        let content = read(destination)?;
+        let chunks = wave_chunks(&content);
+        let sample_rate = chunks
+            .iter()
+            .find(|(id, _)| *id == b"fmt ")
+            .and_then(|(_, data)| data.get(4..8))
+            .map(|rate| u32::from_le_bytes(rate.try_into().unwrap()));
+        ensure!(
+            sample_rate.is_some(),
+            AppliersErrors::AudioFileNotCompatible(
+                destination.display().to_string(),
+                "WAV".to_string()
+            )
+        );
+        let sample_rate = sample_rate.unwrap();
+
+        let mut cues = (chapters.len() as u32).to_le_bytes().to_vec();
+        let mut labels = b"adtl".to_vec();
+        for (idx, chapter) in chapters.iter().enumerate() {
+            let id = (idx as u32 + 1).to_le_bytes();
+            let offset = (chapter.start_time as u64 * sample_rate as u64 / 1000) as u32;
+            cues.extend(id);
+            cues.extend(offset.to_le_bytes());
+            cues.extend(b"data");
+            cues.extend([0; 8]);
+            cues.extend(offset.to_le_bytes());
+
+            let mut label = id.to_vec();
+            label.extend(chapter_title(chapter).as_bytes());
+            label.push(0);
+            push_chunk(&mut labels, b"labl", &label);
+        }
+
+        //Cues left over from a previous run or another editor are replaced, not added to
+        let mut out = b"RIFF\0\0\0\0WAVE".to_vec();
+        for (id, data) in chunks {
+            if id != b"cue " && !(id == b"LIST" && data.starts_with(b"adtl")) {
+                push_chunk(&mut out, id, data);
+            }
+        }
+        push_chunk(&mut out, b"cue ", &cues);
+        push_chunk(&mut out, b"LIST", &labels);
+        let size = (out.len() - 8) as u32;
+        out[4..8].copy_from_slice(&size.to_le_bytes());
+
+        write(destination, out)?;
+        Ok(())
+    }
+}
+
+type RiffChunk<'a> = (&'a [u8], &'a [u8]);
+
+/// Top level chunks of a RIFF/WAVE file, none if it isn't one
+fn wave_chunks(content: &[u8]) -> Vec<RiffChunk<'_>> {
+    match content.get(..12) {
+        Some([b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E']) => {
+            chunks(&content[12..])
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Id and data of every chunk packed in `content`
+fn chunks(content: &[u8]) -> Vec<RiffChunk<'_>> {
+    let mut chunks = Vec::new();
+    let mut offset = 0;
+    while let Some(header) = content.get(offset..offset + 8) {
+        let size = u32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+        //Some writers don't account for the last chunk properly, keep what is there
+        let end = (offset + 8 + size).min(content.len());
+        chunks.push((&header[..4], &content[offset + 8..end]));
+        //Chunks are word aligned
+        offset = end + size % 2;
+    }
+    chunks
+}
+
+fn push_chunk(out: &mut Vec<u8>, id: &[u8], data: &[u8]) {
+    out.extend(id);
+    out.extend((data.len() as u32).to_le_bytes());
+    out.extend(data);
+    if data.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+/// Sample offset and label of every cue point, in the order they are stored
+#[cfg(test)]
+pub fn cue_points(content: &[u8]) -> Vec<(u32, String)> {
+    let top_level = wave_chunks(content);
+    let labels: Vec<_> = top_level
+        .iter()
+        .filter(|(id, data)| *id == b"LIST" && data.starts_with(b"adtl"))
+        .flat_map(|(_, data)| chunks(&data[4..]))
+        .filter(|(id, data)| *id == b"labl" && data.len() >= 4)
+        .map(|(_, data)| {
+            let text = data[4..]
+                .split(|byte| *byte == 0)
+                .next()
+                .unwrap_or_default();
+            (&data[..4], String::from_utf8_lossy(text).to_string())
+        })
+        .collect();
+    let Some((_, cues)) = top_level.iter().find(|(id, _)| *id == b"cue ") else {
+        return Vec::new();
+    };
+    cues[4..]
+        .chunks_exact(24)
+        .map(|cue| {
+            let label = labels.iter().find(|(id, _)| *id == &cue[..4]);
+            (
+                u32::from_le_bytes(cue[20..24].try_into().unwrap()),
+                label.map(|(_, text)| text.clone()).unwrap_or_default(),
+            )
+        })
+        .collect()
+}
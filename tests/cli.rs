@@ -0,0 +1,363 @@
+use std::{
+    env::temp_dir,
+    fs::{copy, create_dir_all, read, remove_dir_all, remove_file, rename},
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+macro_rules! test_file {
+    ($file_name:expr) => {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/resources/test/", $file_name)
+    };
+}
+
+fn presquile(args: &[&str]) -> i32 {
+    let status = Command::new(env!("CARGO_BIN_EXE_presquile"))
+        .args(args)
+        .status()
+        .unwrap();
+    status.code().unwrap()
+}
+
+/// A copy of the fixture without its chapters, which apply refuses to replace unless forced
+fn scratch_mp3(name: &str) -> PathBuf {
+    let dir = temp_dir().join(name);
+    let _ = remove_dir_all(&dir);
+    create_dir_all(&dir).unwrap();
+    let source = dir.join("source.mp3");
+    copy(test_file!("audio.mp3"), &source).unwrap();
+    assert_eq!(presquile(&["-q", "strip", source.to_str().unwrap()]), 0);
+    remove_file(source).unwrap();
+    let mp3_file = dir.join("audio.mp3");
+    rename(dir.join("source_enriched.mp3"), &mp3_file).unwrap();
+    mp3_file
+}
+
+#[test]
+fn apply_exit_codes() {
+    let mp3_file = scratch_mp3("presquile_cli_exit_codes");
+    let mp3_file = mp3_file.to_str().unwrap();
+
+    let apply = |cvs: &str, mp3_file: &str| presquile(&["--quiet", "apply", cvs, mp3_file]);
+    assert_eq!(apply(test_file!("valid_chaps.cvs"), mp3_file), 0);
+    //The enriched file of the first run is still there
+    assert_eq!(apply(test_file!("valid_chaps.cvs"), mp3_file), 9);
+
+    let apply = |cvs: &str, mp3_file: &str| presquile(&["-q", "apply", "--force", cvs, mp3_file]);
+    assert_eq!(apply(test_file!("invalid_chaps.cvs"), mp3_file), 3);
+    assert_eq!(
+        apply(test_file!("valid_chaps.cvs"), test_file!("file.txt")),
+        2
+    );
+}
+
+#[test]
+fn apply_without_subcommand() {
+    let mp3_path = scratch_mp3("presquile_cli_default_command");
+    let mp3_file = mp3_path.to_str().unwrap();
+    assert_eq!(
+        presquile(&["-q", test_file!("valid_chaps.cvs"), mp3_file]),
+        0
+    );
+    //Options of apply may come first, along with the global ones
+    assert_eq!(
+        presquile(&[
+            "--config",
+            test_file!("presquile.toml"),
+            "--force",
+            "-q",
+            test_file!("valid_chaps.cvs"),
+            mp3_file
+        ]),
+        0
+    );
+    //A subcommand named first is still taken as one
+    let enriched = mp3_path.with_file_name("audio_chapters.mp3");
+    assert_eq!(presquile(&["-q", "list", enriched.to_str().unwrap()]), 0);
+}
+
+#[test]
+fn apply_legacy_trailing_subcommand() {
+    let mp3_path = scratch_mp3("presquile_cli_legacy_command");
+    let mp3_file = mp3_path.to_str().unwrap();
+    //The form of the first releases, with the subcommand after the files
+    assert_eq!(
+        presquile(&["-q", test_file!("valid_chaps.cvs"), mp3_file, "apply"]),
+        0
+    );
+    assert!(mp3_path.with_file_name("audio_enriched.mp3").exists());
+    assert_eq!(
+        presquile(&[
+            "-q",
+            test_file!("valid_chaps.cvs"),
+            mp3_file,
+            "apply",
+            "--force"
+        ]),
+        0
+    );
+}
+
+#[test]
+fn apply_enriched_file() {
+    let mp3_file = scratch_mp3("presquile_cli_enriched");
+    let apply = |args: &[&str], mp3_file: &str| {
+        let args = [
+            &["-q", "apply"],
+            args,
+            &[test_file!("valid_chaps.cvs"), mp3_file],
+        ]
+        .concat();
+        presquile(&args)
+    };
+    assert_eq!(apply(&[], mp3_file.to_str().unwrap()), 0);
+
+    let enriched = mp3_file.with_file_name("audio_enriched.mp3");
+    let enriched = enriched.to_str().unwrap();
+    assert_eq!(apply(&[], enriched), 21);
+    assert_eq!(apply(&["--merge"], enriched), 0);
+    assert_eq!(apply(&["--force"], enriched), 0);
+}
+
+#[test]
+fn quiet_prints_nothing() {
+    let mp3_file = scratch_mp3("presquile_cli_quiet");
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_presquile"))
+            .args(args)
+            .output()
+            .unwrap()
+    };
+    let dry_run = run(&[
+        "-q",
+        "apply",
+        "--dry-run",
+        test_file!("valid_chaps.cvs"),
+        mp3_file.to_str().unwrap(),
+    ]);
+    assert_eq!(dry_run.status.code(), Some(0));
+    assert!(dry_run.stdout.is_empty());
+
+    let list = run(&["-q", "list", test_file!("audio.mp3")]);
+    assert_eq!(list.status.code(), Some(0));
+    assert!(list.stdout.is_empty());
+    assert!(!run(&["list", test_file!("audio.mp3")]).stdout.is_empty());
+}
+
+#[test]
+fn verify_exit_codes() {
+    assert_eq!(
+        presquile(&["-q", "verify", test_file!("valid_chaps.cvs")]),
+        0
+    );
+    assert_eq!(
+        presquile(&["-q", "verify", test_file!("overlapping_chaps.cvs")]),
+        7
+    );
+    assert_eq!(presquile(&["-q", "verify", test_file!("missing.cvs")]), 1);
+}
+
+#[test]
+fn verify_like_apply() {
+    //Global options go before the subcommand, its own ones after
+    let verify = |global: &[&str], flags: &[&str]| {
+        let mut args = vec!["-q"];
+        args.extend_from_slice(global);
+        args.push("verify");
+        args.extend_from_slice(flags);
+        args.push(test_file!("mixed_rows_chaps.cvs"));
+        presquile(&args)
+    };
+    let config = ["--config", test_file!("presquile.toml")];
+    assert_eq!(verify(&[], &[]), 5);
+    //The parsing flags and the config file of apply are read here too
+    assert_eq!(verify(&[], &["--lenient"]), 0);
+    assert_eq!(verify(&config, &[]), 0);
+    assert_eq!(verify(&config, &["--no-lenient"]), 5);
+}
+
+#[test]
+fn apply_from_stdin() {
+    let mp3_file = scratch_mp3("presquile_cli_stdin");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_presquile"))
+        .args(["-q", "apply", "-", mp3_file.to_str().unwrap()])
+        .stdin(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let markers = read(test_file!("valid_chaps.cvs")).unwrap();
+    child.stdin.take().unwrap().write_all(&markers).unwrap();
+
+    assert_eq!(child.wait().unwrap().code(), Some(0));
+    assert!(mp3_file.with_file_name("audio_enriched.mp3").exists());
+}
+
+#[test]
+fn apply_with_config_file() {
+    let mp3_file = scratch_mp3("presquile_cli_config");
+    let dir = mp3_file.parent().unwrap();
+    copy(test_file!("presquile.toml"), dir.join("presquile.toml")).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_presquile"))
+        .args(["-v", "apply", test_file!("valid_chaps.cvs"), "audio.mp3"])
+        .current_dir(dir)
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(0));
+    //Without --parallel the mode comes from the config file
+    assert!(String::from_utf8_lossy(&output.stderr).contains("in Parallel mode"));
+    assert!(dir.join("audio_chapters.mp3").exists());
+}
+
+#[test]
+fn apply_overriding_config_file() {
+    let mp3_file = scratch_mp3("presquile_cli_config_override");
+    let dir = mp3_file.parent().unwrap();
+    copy(test_file!("presquile.toml"), dir.join("presquile.toml")).unwrap();
+    let apply = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_presquile"))
+            .args(["-v", "apply", "--force"])
+            .args(args)
+            .args([test_file!("mixed_rows_chaps.cvs"), "audio.mp3"])
+            .current_dir(dir)
+            .output()
+            .unwrap()
+    };
+
+    //The config file turns lenient and parallel on, the flags turn them back off
+    let output = apply(&["--no-lenient"]);
+    assert_eq!(output.status.code(), Some(5));
+    let output = apply(&["--sequential"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("in Sequential mode"));
+    //The last of two opposite flags wins
+    let output = apply(&["--no-lenient", "--lenient", "--sequential", "--parallel"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("in Parallel mode"));
+}
+
+#[test]
+fn strip_output_options() {
+    let mp3_path = scratch_mp3("presquile_cli_strip");
+    let mp3_file = mp3_path.to_str().unwrap();
+    assert_eq!(
+        presquile(&["-q", "apply", test_file!("valid_chaps.cvs"), mp3_file]),
+        0
+    );
+    let enriched = mp3_path.with_file_name("audio_enriched.mp3");
+    let out = mp3_path.with_file_name("out");
+    assert_eq!(
+        presquile(&[
+            "-q",
+            "strip",
+            "--suffix",
+            "_clean",
+            "--output-dir",
+            out.to_str().unwrap(),
+            enriched.to_str().unwrap()
+        ]),
+        0
+    );
+    assert!(out.join("audio_enriched_clean.mp3").exists());
+
+    //Like apply, strip keeps an existing output unless forced
+    let strip = |extra: &[&str]| {
+        let mut args = vec!["-q", "strip", "--suffix", "_clean"];
+        args.extend_from_slice(extra);
+        args.push(enriched.to_str().unwrap());
+        presquile(&args)
+    };
+    assert_eq!(strip(&[]), 0);
+    assert_eq!(strip(&[]), 9);
+    assert_eq!(strip(&["--force"]), 0);
+}
+
+#[test]
+fn batch_force() {
+    let mp3_file = scratch_mp3("presquile_cli_batch_force");
+    let dir = mp3_file.parent().unwrap();
+    copy(test_file!("valid_chaps.cvs"), dir.join("audio.cvs")).unwrap();
+    let dir_arg = dir.to_str().unwrap();
+
+    assert_eq!(presquile(&["-q", "batch", dir_arg]), 0);
+    //The outputs of the first run are kept unless forced
+    assert_eq!(presquile(&["-q", "batch", dir_arg]), 1);
+    assert_eq!(presquile(&["-q", "batch", "--force", dir_arg]), 0);
+}
+
+#[test]
+fn batch_pattern() {
+    let mp3_file = scratch_mp3("presquile_cli_batch_pattern");
+    let dir = mp3_file.parent().unwrap();
+    copy(test_file!("valid_chaps.cvs"), dir.join("audio.cvs")).unwrap();
+    copy(test_file!("audio.mp3"), dir.join("other.mp3")).unwrap();
+    copy(test_file!("valid_chaps.cvs"), dir.join("other.cvs")).unwrap();
+
+    let pattern = dir.join("*.mp3");
+    assert_eq!(presquile(&["-q", "batch", pattern.to_str().unwrap()]), 0);
+    assert!(dir.join("audio_enriched.mp3").exists());
+    assert!(dir.join("other_enriched.mp3").exists());
+
+    let pattern = dir.join("*.flac");
+    assert_eq!(presquile(&["-q", "batch", pattern.to_str().unwrap()]), 17);
+}
+
+#[test]
+fn apply_pattern() {
+    let mp3_file = scratch_mp3("presquile_cli_apply_pattern");
+    let dir = mp3_file.parent().unwrap();
+    copy(&mp3_file, dir.join("other.mp3")).unwrap();
+
+    let pattern = dir.join("*.mp3");
+    let apply = |pattern: &str| presquile(&["-q", "apply", test_file!("valid_chaps.cvs"), pattern]);
+    assert_eq!(apply(pattern.to_str().unwrap()), 0);
+    assert!(dir.join("audio_enriched.mp3").exists());
+    assert!(dir.join("other_enriched.mp3").exists());
+
+    let pattern = dir.join("*.flac");
+    assert_eq!(apply(pattern.to_str().unwrap()), 17);
+}
+
+#[test]
+fn apply_json() {
+    let run = |mp3_file: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_presquile"))
+            .args([
+                "apply",
+                "--json",
+                test_file!("out_of_range_chaps.cvs"),
+                mp3_file,
+            ])
+            .output()
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        (output.status.code(), json)
+    };
+    let mp3_file = scratch_mp3("presquile_cli_json");
+
+    let (code, json) = run(mp3_file.to_str().unwrap());
+    assert_eq!(code, Some(0));
+    let output = mp3_file.with_file_name("audio_enriched.mp3");
+    assert_eq!(json["output"], output.to_str().unwrap());
+    assert_eq!(json["chapter_count"], 2);
+    assert!(json["total_duration_ms"].as_u64().is_some_and(|ms| ms > 0));
+    assert_eq!(json["warnings"].as_array().map(Vec::len), Some(2));
+
+    let (code, json) = run(mp3_file.to_str().unwrap());
+    assert_eq!(code, Some(9));
+    assert_eq!(json["error"], "OutputExists");
+    assert!(json["message"]
+        .as_str()
+        .is_some_and(|message| message.contains("audio_enriched.mp3")));
+}
+
+#[test]
+fn bash_completions() {
+    let output = Command::new(env!("CARGO_BIN_EXE_presquile"))
+        .args(["completions", "bash"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("apply"));
+}